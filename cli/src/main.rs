@@ -1,17 +1,42 @@
-use std::time::Duration;
+use std::collections::HashMap;
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use color_eyre::eyre::eyre;
 use cote::prelude::Cote;
-use reqwest::{Client, cookie::Jar};
-use search::QueryInput;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use reqwest::{Client, ClientBuilder, cookie::Jar, header::HeaderMap};
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use search::Exchange;
+use search::MatchPreference;
+use search::NormalizeStyle;
+use search::Script;
 use search::Search;
+use search::SearchOptions;
+use search::SecurityKind;
 use search::Stock;
+use search::Timeouts;
 use search::Tool;
+use search::batch::{Batch, BatchReport, Progress, Reporter, RetryConfig, SearchError};
 use search::cfi;
 use search::cninfo;
+use search::connect::ConnectList;
+use search::export::{ExportFormat, OnMissing, OutputEncoding};
 use search::hexun;
+use search::limiter::RateLimiter;
+use search::offline::Offline;
+use search::overrides::Overrides;
+use search::probe::Probe;
 use search::sina;
 use search::sohu;
-use tokio::time::sleep;
+use search::synonyms::Synonyms;
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "server")]
+mod server;
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
@@ -26,25 +51,548 @@ struct Cli {
     #[arg(value = 50usize)]
     delay: Option<usize>,
 
-    /// Select search tools
-    #[arg(alias = "-t", scvalues = ["cninfo", "sina", "cfi", "hexun"], value = Tool::CnInfo)]
-    tool: Option<Tool>,
+    /// Add a random delay between 0 and the milliseconds to each task delay
+    #[arg(name = "--delay-jitter", value = 0usize)]
+    delay_jitter: Option<usize>,
+
+    /// Set the seed of the delay jitter
+    seed: Option<u64>,
+
+    /// Search at most N keywords at the same time, default is 1, the output keeps the
+    /// order of the keywords
+    concurrency: Option<usize>,
+
+    /// Limit the concurrent searches of the tools, such as `hexun=2,sina=8`, the tools
+    /// not in it follow `--concurrency`
+    #[arg(name = "--tool-concurrency")]
+    tool_concurrency: Option<String>,
+
+    /// Send at most N requests per second in total, shared by all the tools of `--tool all`
+    #[arg(name = "--global-rps")]
+    global_rps: Option<f64>,
+
+    /// Set the milliseconds of the connect timeout of each request
+    #[arg(name = "--connect-timeout")]
+    connect_timeout: Option<usize>,
+
+    /// Set the milliseconds of the timeout of each whole request
+    #[arg(name = "--read-timeout")]
+    read_timeout: Option<usize>,
+
+    /// Select search tools, the aliases and unambiguous prefixes are accepted, `all`
+    /// races all the tools and takes the first non-empty results
+    #[arg(alias = "-t", scvalues = ["all", "cninfo", "cn", "cni", "juchao", "sina", "sn", "cfi", "cf", "zcw", "hexun", "hx", "sohu", "so"])]
+    tool: Option<String>,
+
+    /// Query the tools of `--tool all` one by one in order instead of racing them,
+    /// it is slower but the output is reproducible between runs
+    stable: bool,
+
+    /// Retry the failed keywords with another search tool, the keywords not searched
+    /// after the abort of `--max-failures` are also retried
+    #[arg(name = "--retry-tool", scvalues = ["cninfo", "sina", "cfi", "hexun", "sohu"])]
+    retry_tool: Option<String>,
+
+    /// Search a keyword again with the same tool when nothing is found
+    #[arg(name = "--retry-on-empty")]
+    retry_on_empty: bool,
+
+    /// Search a keyword again with the same tool when the request failed, such as a
+    /// transient network error
+    #[arg(name = "--retry-on-error")]
+    retry_on_error: bool,
+
+    /// Print more details of the search, such as the tool resolved each keyword
+    #[arg(alias = "-v")]
+    verbose: bool,
+
+    /// Print the min, median and p95 elapsed time of the requests of each tool
+    timings: bool,
+
+    /// The tool whose name is kept when the tools return different names of a code,
+    /// default is cninfo
+    authority: Option<String>,
+
+    /// Skip the reachability check of the tools
+    #[arg(name = "--no-probe")]
+    no_probe: bool,
+
+    /// Check the reachability of the tools with the url instead of their homepage
+    #[arg(name = "--probe-url")]
+    probe_url: Option<String>,
+
+    /// Abort after the number of consecutive failed requests, the keywords found nothing
+    /// are not counted, 0 means never abort
+    #[arg(name = "--max-failures", value = 1usize)]
+    max_failures: Option<usize>,
+
+    /// Only process the first N results of each keyword
+    limit: Option<usize>,
+
+    /// Fail the keywords longer than N characters before sending the request, default
+    /// is 64, it catches the paragraphs pasted as one keyword
+    #[arg(name = "--max-keyword-len")]
+    max_keyword_len: Option<usize>,
+
+    /// Load the `code,exchange` overrides from the csv or json file
+    overrides: Option<PathBuf>,
+
+    /// Load the Stock Connect (沪港通/深港通) eligible codes from the file, one per line.
+    /// Without it the eligibility is guessed, which only rules out the obvious cases
+    /// such as the Beijing and `ST` stocks
+    #[arg(name = "--connect-list")]
+    connect_list: Option<PathBuf>,
+
+    /// Only accept the index results, such as 上证指数, supported by sohu
+    index: bool,
+
+    /// Only accept the stocks of the comma separated exchanges, such as `sh,sz,bj`
+    exchanges: Option<String>,
+
+    /// Rank the candidates by the comma separated exchanges, such as `sz,sh` prefers
+    /// Shenzhen when a name is listed in both, the A/H preference of `--prefer` still applies
+    #[arg(name = "--prefer-exchange-order")]
+    prefer_exchange_order: Option<String>,
+
+    /// Prefer the candidates whose name or alias equals the keyword, such as an old name
+    #[arg(name = "--match-aliases")]
+    match_aliases: bool,
+
+    /// Select the listing of the candidates, one of a, h, any, default a prefers the
+    /// mainland stocks, h prefers the HK stocks, any takes the first candidate
+    prefer: Option<String>,
+
+    /// Convert the names of the HK stocks to the script, one of trad, simp, such as
+    /// `simp` makes them consistent with the mainland names
+    #[arg(name = "--hk-script")]
+    hk_script: Option<String>,
+
+    /// Set the exchange hint, one of sh, sz, bj, hk, us
+    #[arg(alias = "-e")]
+    exchange: Option<String>,
+
+    /// Expand the abbreviations such as `茅台,贵州茅台` in the csv file before
+    /// searching, the file is loaded over the bundled abbreviations, which are
+    /// used without it
+    aliases: Option<PathBuf>,
+
+    /// Convert the traditional chinese keywords to simplified before searching
+    simplify: bool,
+
+    /// Output the keywords which are already codes such as `600519` directly, only
+    /// the names are searched
+    #[arg(name = "--codes-passthrough")]
+    codes_passthrough: bool,
+
+    /// Resolve the common securities by the bundled table before searching, only the
+    /// names not in it are searched, requires the `offline` feature
+    #[arg(name = "--offline-first")]
+    offline_first: bool,
+
+    /// Warn about the stocks whose code does not belong to its exchange
+    validate: bool,
+
+    /// Ask which candidate to use when a keyword matches multiple stocks
+    #[arg(alias = "-i")]
+    interactive: bool,
+
+    /// Look up the industry of each resolved stock, costs an extra request per stock
+    enrich: bool,
+
+    /// Keep the keyword of each stock in the `query` column of the csv and json output
+    #[arg(name = "--keep-query")]
+    keep_query: bool,
+
+    /// Remove the duplicate stocks from the output
+    unique: bool,
+
+    /// Output all the candidates of each keyword instead of the first one, the
+    /// keyword is kept in the `query` column
+    #[arg(name = "--all-matches")]
+    all_matches: bool,
+
+    /// List the top N candidates of each keyword grouped by the keyword instead of
+    /// resolving it, such as `--explore 10 中国`, the `--format` is ignored. The
+    /// `--limit` bounds the rows scanned of each response
+    explore: Option<usize>,
+
+    /// Set the output format, one of ebk, csv, txt-names, json, ndjson, table, the
+    /// ndjson lines are printed as each keyword resolves
+    #[arg(alias = "-f")]
+    format: Option<String>,
+
+    /// Set the style of the normalized codes, one of tdx, prefix, suffix, plain, yahoo,
+    /// only the tdx style can be imported by 通达信
+    style: Option<String>,
+
+    /// Read the codes from the `.ebk` or `.blk` block file and write them in the
+    /// `--format` and `--style` without searching
+    import: Option<PathBuf>,
+
+    /// Merge the block files into one without searching, the duplicated codes are kept
+    /// once, such as `merge a.ebk b.ebk -o all.ebk`
+    #[sub]
+    merge: Option<Merge>,
+
+    /// Check each code of the `.ebk` or `.blk` block file without searching, print
+    /// the invalid lines with the line numbers and fail if any, such as `validate a.ebk`
+    #[sub(name = "validate")]
+    validate_file: Option<Validate>,
+
+    /// Write the output to the file instead of stdout
+    #[arg(alias = "-o")]
+    output: Option<PathBuf>,
+
+    /// Set the encoding of the output, one of utf8, gbk, some 通达信 and 同花顺 installs
+    /// show the names of the utf8 files garbled
+    #[arg(name = "--output-encoding")]
+    output_encoding: Option<String>,
+
+    /// Append to the output file and skip the stocks already in it, default is overwrite
+    append: bool,
+
+    /// Start the output with a comment such as `# generated by stockconverter at <time>,
+    /// tool=cninfo, count=3`, only the csv, txt-names and table formats have the comment
+    #[arg(name = "--comment-header")]
+    comment_header: bool,
+
+    /// Print the count of the resolved keywords only instead of the stocks
+    count: bool,
+
+    /// Exit with an error if any keyword failed
+    strict: bool,
+
+    /// Set what to output for the keywords found nothing, one of error, skip, placeholder,
+    /// default is skip. The placeholder such as `# 平安银行: not found` keeps the output
+    /// lines aligned with the input
+    #[arg(name = "--on-missing")]
+    on_missing: Option<String>,
+
+    /// Load and save the cookies in the file between runs
+    #[arg(name = "--cookie-store")]
+    cookie_store: Option<PathBuf>,
+
+    /// Write the response body of each request to the directory before parsing, the
+    /// files are named by the keyword and the tool, such as `贵州茅台.sina.txt`
+    #[arg(name = "--dump-dir")]
+    dump_dir: Option<PathBuf>,
+
+    /// Send the requests of the tool to the url instead, such as a mirror or a mock
+    /// server, it does not work with `--tool all`
+    #[arg(name = "--base-url")]
+    base_url: Option<String>,
+
+    /// Write the summary of the resolved and the failed keywords with the errors to
+    /// the file in json, the failed keywords are the ones failed after the retries
+    report: Option<PathBuf>,
+
+    /// Send the header of the `Name: value` syntax with each request, such as
+    /// `--header 'Referer: https://example.com'`, it can be repeated
+    header: Option<Vec<String>>,
+
+    /// Send the cookie of the `name=value` syntax with each request, it can be repeated,
+    /// the cookies replace the ones of the session and `--cookie-store`
+    cookie: Option<Vec<String>>,
+
+    /// Serve the `/resolve` HTTP endpoint on the port instead of searching
+    serve: Option<u16>,
+
+    /// Print available search tools and exit
+    #[arg(name = "--list-tools")]
+    list_tools: bool,
+
+    /// Check the reachability of each tool, used with `--list-tools`
+    check: bool,
+
+    /// Resolve the built-in samples with the tool, all the tools with `--tool all`,
+    /// print the result of each and exit, fail if any is not resolved as expected
+    selftest: bool,
+
+    /// Split each keyword line by the characters, default is `,，、;；`, empty disables it
+    #[arg(name = "--split-chars")]
+    split_chars: Option<String>,
+
+    /// Print how the input keywords are interpreted and exit without searching
+    inspect: bool,
+
+    /// Read the keywords from the clipboard, one per line. The keywords of the
+    /// arguments go first, then the clipboard, then the stdin
+    #[arg(name = "--from-clipboard")]
+    from_clipboard: bool,
 
     /// Set the search keyword
     #[pos(index = 1..)]
     keywords: Option<Vec<String>>,
 }
 
+/// The `merge` sub command, combine the block files without network.
+#[derive(Debug, Cote)]
+#[cote(aborthelp)]
+struct Merge {
+    /// Set the output format, one of ebk, csv, txt-names, json, ndjson, table
+    #[arg(alias = "-f")]
+    format: Option<String>,
+
+    /// Set the style of the normalized codes, one of tdx, prefix, suffix, plain, yahoo,
+    /// only the tdx style can be imported by 通达信
+    style: Option<String>,
+
+    /// Write the output to the file instead of stdout
+    #[arg(alias = "-o")]
+    output: Option<PathBuf>,
+
+    /// Set the encoding of the output, one of utf8, gbk
+    #[arg(name = "--output-encoding")]
+    output_encoding: Option<String>,
+
+    /// Set the `.ebk` or `.blk` block files to merge
+    #[pos(index = 1..)]
+    inputs: Option<Vec<PathBuf>>,
+}
+
+impl Merge {
+    pub fn invoke(self) -> color_eyre::Result<()> {
+        let inputs = self.inputs.unwrap_or_default();
+
+        if inputs.is_empty() {
+            return Err(eyre!("The `merge` requires the block files"));
+        }
+        let format = self
+            .format
+            .map(|v| v.parse::<ExportFormat>())
+            .transpose()?
+            .unwrap_or_default();
+        let style = self
+            .style
+            .map(|v| v.parse::<NormalizeStyle>())
+            .transpose()?
+            .unwrap_or_default();
+        let encoding = self
+            .output_encoding
+            .map(|v| v.parse::<OutputEncoding>())
+            .transpose()?
+            .unwrap_or_default();
+        let stocks = search::block::merge(&inputs)?;
+
+        match &self.output {
+            Some(output) => write_output(output, format, style, encoding, stocks, false, None),
+            None => print_output(&format.export_with(&stocks, style)?, encoding),
+        }
+    }
+}
+
+/// The `validate` sub command, check the codes of the block file without network.
+#[derive(Debug, Cote)]
+#[cote(aborthelp)]
+struct Validate {
+    /// Set the `.ebk` or `.blk` block file to check
+    #[pos(index = 1)]
+    input: Option<PathBuf>,
+}
+
+impl Validate {
+    pub fn invoke(self) -> color_eyre::Result<()> {
+        let input = self
+            .input
+            .ok_or_else(|| eyre!("The `validate` requires the block file"))?;
+
+        validate_block_file(&input)
+    }
+}
+
 async fn inner_main() -> color_eyre::Result<()> {
     let Cli {
         delay,
+        delay_jitter,
+        seed,
+        concurrency,
+        tool_concurrency,
+        global_rps,
+        connect_timeout,
+        read_timeout,
         tool,
+        stable,
+        retry_tool,
+        retry_on_empty,
+        retry_on_error,
+        verbose,
+        timings,
+        authority,
+        no_probe,
+        probe_url,
+        max_failures,
+        limit,
+        max_keyword_len,
+        overrides,
+        connect_list,
+        index,
+        exchanges,
+        prefer_exchange_order,
+        match_aliases,
+        prefer,
+        hk_script,
+        exchange,
+        aliases,
+        simplify,
+        codes_passthrough,
+        offline_first,
+        validate,
+        interactive,
+        enrich,
+        keep_query,
+        unique,
+        all_matches,
+        explore,
+        format,
+        style,
+        import,
+        merge,
+        validate_file,
+        output,
+        output_encoding,
+        append,
+        comment_header,
+        count,
+        strict,
+        on_missing,
+        cookie_store,
+        dump_dir,
+        base_url,
+        report,
+        header,
+        cookie,
+        serve,
+        list_tools,
+        check,
+        selftest,
+        split_chars,
+        inspect,
+        from_clipboard,
         keywords,
     } = Cli::parse_env()?;
+
+    if let Some(merge) = merge {
+        return merge.invoke();
+    }
+    if let Some(validate) = validate_file {
+        return validate.invoke();
+    }
+
+    let millis = |v: usize| Duration::from_millis(v as u64);
+    let timeouts = Timeouts::default()
+        .with_connect(connect_timeout.map(millis))
+        .with_read(read_timeout.map(millis));
+    let headers = search::extra_headers(&header.unwrap_or_default(), &cookie.unwrap_or_default())?;
+
+    if list_tools {
+        return print_tools(check, timeouts, headers).await;
+    }
+    if let Some(port) = serve {
+        // the upstream requests are spaced by `--delay` without `--global-rps`
+        let limiter = match (global_rps, delay.unwrap()) {
+            (Some(rps), _) => Some(RateLimiter::new(rps)?),
+            (None, 0) => None,
+            (None, delay) => Some(RateLimiter::new(1000.0 / delay as f64)?),
+        };
+
+        return serve_on(port, limiter, timeouts, headers).await;
+    }
+
     let mut keywords = keywords.unwrap_or_default();
-    let tool = tool.unwrap();
+    // `None` means all the tools
+    let tool = match tool {
+        Some(v) if v.eq_ignore_ascii_case("all") => None,
+        v => Some(
+            v.map(|v| v.parse::<Tool>())
+                .transpose()?
+                .unwrap_or_default(),
+        ),
+    };
+    let retry_tool = retry_tool.map(|v| v.parse::<Tool>()).transpose()?;
+
+    if tool.is_none() && base_url.is_some() {
+        return Err(eyre!("The `--base-url` can not be used with `--tool all`"));
+    }
+    let authority = authority
+        .map(|v| v.parse::<Tool>())
+        .transpose()?
+        .unwrap_or(Tool::CnInfo);
+    let probe = match (no_probe, probe_url) {
+        (true, _) => Probe::Skip,
+        (false, Some(url)) => Probe::Url(url),
+        (false, None) => Probe::Homepage,
+    };
+
+    if selftest {
+        return run_selftest(tool, timeouts, headers, &probe).await;
+    }
     let delay = delay.unwrap();
+    let delay_jitter = delay_jitter.unwrap();
+    let rng = seed.map_or_else(StdRng::from_os_rng, StdRng::seed_from_u64);
+    let max_failures = max_failures.unwrap();
+    let exchange = exchange.map(|v| v.parse::<Exchange>()).transpose()?;
+    let prefer = prefer
+        .map(|v| v.parse::<MatchPreference>())
+        .transpose()?
+        .unwrap_or_default();
+    let hk_script = hk_script.map(|v| v.parse::<Script>()).transpose()?;
+    let exchanges = parse_exchanges(exchanges.as_deref())?;
+    let exchange_order = parse_exchanges(prefer_exchange_order.as_deref())?;
+    let overrides = overrides
+        .as_deref()
+        .map(Overrides::load)
+        .transpose()?
+        .unwrap_or_default();
+    let connect_list = connect_list.as_deref().map(ConnectList::load).transpose()?;
+    // the file is loaded over the bundled abbreviations
+    let synonyms = match aliases.as_deref() {
+        Some(path) => Synonyms::load(path)?,
+        None => Synonyms::bundled()?,
+    };
+    let format = format
+        .map(|v| v.parse::<ExportFormat>())
+        .transpose()?
+        .unwrap_or_default();
+    let style = style
+        .map(|v| v.parse::<NormalizeStyle>())
+        .transpose()?
+        .unwrap_or_default();
+    let encoding = output_encoding
+        .map(|v| v.parse::<OutputEncoding>())
+        .transpose()?
+        .unwrap_or_default();
+    let on_missing = on_missing
+        .map(|v| v.parse::<OnMissing>())
+        .transpose()?
+        .unwrap_or_default();
 
+    if on_missing == OnMissing::Placeholder && (unique || append) {
+        return Err(eyre!(
+            "The placeholder lines can not align with the input with `--unique` or `--append`"
+        ));
+    }
+
+    if let Some(path) = import {
+        let stocks = search::block::read(&path)?;
+        let comment =
+            comment_header.then(|| search::export::generated_comment("import", stocks.len()));
+        let comment = comment.as_deref();
+
+        return match &output {
+            Some(output) => write_output(output, format, style, encoding, stocks, append, comment),
+            None => print_output(
+                &with_comment(format, format.export_with(&stocks, style)?, comment),
+                encoding,
+            ),
+        };
+    }
+    if from_clipboard {
+        let text = arboard::Clipboard::new()
+            .and_then(|mut v| v.get_text())
+            .map_err(|e| eyre!("Can not read keywords from the clipboard: {e}"))?;
+
+        keywords.push(text);
+    }
     if !atty::is(atty::Stream::Stdin) {
         let mut buff = String::default();
 
@@ -58,101 +606,1044 @@ async fn inner_main() -> color_eyre::Result<()> {
         }
     }
 
+    let split_chars = split_chars.as_deref().unwrap_or(search::SPLIT_CHARS);
+    let lines = keywords;
+    let keywords: Vec<String> = lines
+        .iter()
+        .flat_map(|v| search::split_keywords(v, split_chars))
+        .collect();
+
+    if inspect {
+        print_inspect(&lines, &keywords, &synonyms);
+        return Ok(());
+    }
+
+    let cancel = CancellationToken::new();
+
+    tokio::spawn(interrupt(cancel.clone()));
+
     Searcher {
         tool,
+        stable,
+        retry_tool,
+        retry: RetryConfig::default()
+            .with_retry_on_empty(retry_on_empty)
+            .with_retry_on_error(retry_on_error),
+        verbose,
+        timings: timings.then(Vec::new),
+        timeouts,
+        authority,
+        connect_list,
+        probe,
+        cancel,
         delay,
+        delay_jitter,
+        rng,
+        concurrency: concurrency.unwrap_or(1),
+        tool_concurrency: tool_concurrency
+            .as_deref()
+            .map(search::all::parse_tool_concurrency)
+            .transpose()?
+            .unwrap_or_default(),
+        limiter: global_rps.map(RateLimiter::new).transpose()?,
+        max_failures,
         keywords,
+        synonyms,
+        simplify,
+        codes_passthrough,
+        offline: if offline_first {
+            bundled_offline()?
+        } else {
+            Offline::default()
+        },
+        validate,
+        interactive,
+        enrich,
+        keep_query,
+        unique,
+        all_matches: all_matches || explore.is_some(),
+        explore,
+        format,
+        style,
+        output,
+        encoding,
+        append,
+        comment_header,
+        count,
+        strict,
+        on_missing,
+        cookie_store,
+        dump_dir,
+        base_url,
+        abort: None,
+        errors: report.is_some().then(HashMap::new),
+        report,
+        headers,
+        options: SearchOptions::default()
+            .with_exchange_hint(exchange)
+            .with_limit(limit)
+            .with_kind(index.then_some(SecurityKind::Index))
+            .with_allowed_exchanges(exchanges)
+            .with_also_match_aliases(match_aliases)
+            .with_overrides(overrides)
+            .with_prefer(prefer)
+            .with_exchange_order(exchange_order)
+            .with_hk_script(hk_script)
+            .with_max_keyword_chars(max_keyword_len),
     }
     .invoke()
     .await
 }
 
+/// Parse the comma separated exchanges, such as `sh,sz,bj`.
+fn parse_exchanges(val: Option<&str>) -> color_eyre::Result<Vec<Exchange>> {
+    val.iter()
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::parse::<Exchange>)
+        .collect()
+}
+
+fn client_builder(timeouts: Timeouts, headers: HeaderMap) -> ClientBuilder {
+    timeouts
+        .apply(search::decompression(Client::builder()))
+        .user_agent(search::USER_AGENT)
+        .default_headers(headers)
+        .cookie_store(true)
+        .cookie_provider(Jar::default().into())
+}
+
+async fn print_tools(
+    check: bool,
+    timeouts: Timeouts,
+    headers: HeaderMap,
+) -> color_eyre::Result<()> {
+    let client = client_builder(timeouts, headers).build()?;
+
+    for tool in Tool::ALL {
+        let mut line = format!("{}\t{}\t{}", tool.name(), tool.title(), tool.homepage());
+
+        if check {
+            match search::probe::check(&client, tool.homepage(), tool.name()).await {
+                Ok(_) => line.push_str("\tup"),
+                Err(e) => line.push_str(&format!("\tdown: {e}")),
+            }
+        }
+        println!("{line}");
+    }
+
+    Ok(())
+}
+
+/// Resolve the samples of [`search::selftest`] with the tools, `None` means all the tools.
+async fn run_selftest(
+    tool: Option<Tool>,
+    timeouts: Timeouts,
+    headers: HeaderMap,
+    probe: &Probe,
+) -> color_eyre::Result<()> {
+    let client = client_builder(timeouts, headers).build()?;
+    let tools = tool.map_or_else(|| Tool::ALL.to_vec(), |v| vec![v]);
+    let mut failed = 0;
+
+    for tool in tools {
+        let outcomes = match search::all::Source::init_with_probe(tool, client.clone(), probe).await
+        {
+            Ok(source) => search::selftest::run(&source).await,
+            Err(e) => {
+                failed += search::selftest::samples_of(tool).len();
+                println!("{}\tfail\tinit: {e}", tool.name());
+                continue;
+            }
+        };
+
+        for outcome in outcomes {
+            let sample = outcome.sample;
+            let result = if outcome.passed() { "pass" } else { "fail" };
+            let actual = match &outcome.actual {
+                Ok(code) => code.clone(),
+                Err(e) => format!("error: {e}"),
+            };
+
+            if !outcome.passed() {
+                failed += 1;
+            }
+            println!(
+                "{}\t{result}\t{}\texpect {}, got {actual}",
+                tool.name(),
+                sample.keyword,
+                sample.code
+            );
+        }
+    }
+    if failed > 0 {
+        return Err(eyre!("The selftest failed {failed} samples"));
+    }
+    Ok(())
+}
+
+fn load_cookies(path: &Path) -> color_eyre::Result<CookieStore> {
+    if !path.exists() {
+        return Ok(CookieStore::default());
+    }
+    let file = std::fs::File::open(path)?;
+
+    cookie_store::serde::json::load(std::io::BufReader::new(file))
+        .map_err(|e| eyre!("Can not load cookies from {}: {e}", path.display()))
+}
+
+fn save_cookies(path: &Path, cookies: &CookieStoreMutex) -> color_eyre::Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let store = cookies
+        .lock()
+        .map_err(|_| eyre!("Can not lock the cookie store"))?;
+
+    cookie_store::serde::json::save_incl_expired_and_nonpersistent(&store, &mut file)
+        .map_err(|e| eyre!("Can not save cookies to {}: {e}", path.display()))
+}
+
+/// Print the breakdown of the input lines and the keywords split from them.
+fn print_inspect(lines: &[String], keywords: &[String], synonyms: &Synonyms) {
+    let lines: Vec<_> = lines.iter().flat_map(|v| v.split('\n')).collect();
+    let blank = lines.iter().filter(|v| v.trim().is_empty()).count();
+    let unique: std::collections::HashSet<_> = keywords.iter().collect();
+    let codes = keywords
+        .iter()
+        .filter(|v| search::normalize_stock_number(v).is_some())
+        .count();
+
+    println!("lines: {}", lines.len());
+    println!("blank lines: {blank}");
+    println!("keywords: {}", keywords.len());
+    println!("keywords after dedup: {}", unique.len());
+    println!("already codes: {codes}");
+    println!("names to search: {}", keywords.len() - codes);
+    for keyword in keywords {
+        let expanded = synonyms.expand(keyword);
+
+        if expanded != keyword {
+            println!("expanded: {keyword} -> {expanded}");
+        }
+    }
+}
+
+/// Print the min, median and p95 elapsed time of each tool to stderr.
+fn print_timings(timings: &[(Tool, Duration)]) {
+    for tool in Tool::ALL {
+        let mut elapsed: Vec<_> = timings
+            .iter()
+            .filter(|(v, _)| *v == tool)
+            .map(|(_, v)| *v)
+            .collect();
+
+        if elapsed.is_empty() {
+            continue;
+        }
+        elapsed.sort();
+
+        let percentile = |p: usize| elapsed[(elapsed.len() - 1) * p / 100];
+
+        eprintln!(
+            "timings {}: count {}, min {:?}, median {:?}, p95 {:?}",
+            tool.name(),
+            elapsed.len(),
+            elapsed[0],
+            percentile(50),
+            percentile(95)
+        );
+    }
+}
+
+/// Write the stocks to the file, in append mode the stocks already in the file are skipped.
+fn write_output(
+    path: &Path,
+    format: ExportFormat,
+    style: NormalizeStyle,
+    encoding: OutputEncoding,
+    stocks: Vec<Stock>,
+    append: bool,
+    comment: Option<&str>,
+) -> color_eyre::Result<()> {
+    if !append {
+        let content = with_comment(format, format.export_with(&stocks, style)?, comment);
+
+        std::fs::write(path, encoding.encode(&content)?)?;
+        return Ok(());
+    }
+    if format == ExportFormat::Json {
+        return Err(eyre!(
+            "The `--append` mode does not support the json format"
+        ));
+    }
+    let existing = if path.exists() {
+        encoding.decode(&std::fs::read(path)?)
+    } else {
+        String::default()
+    };
+    let contains = |stock: &Stock| {
+        existing.lines().any(|line| match format {
+            ExportFormat::TxtNames => line.trim() == stock.name,
+            ExportFormat::Csv => line.split(',').any(|v| v == stock.normalize_with(style)),
+            ExportFormat::Ndjson => line.contains(&format!("\"code\":\"{}\"", stock.code)),
+            _ => line.trim() == stock.normalize_with(style),
+        })
+    };
+    let stocks: Vec<_> = stocks.into_iter().filter(|v| !contains(v)).collect();
+    let mut content = format.export_with(&stocks, style)?;
+
+    if format == ExportFormat::Csv && !existing.is_empty() {
+        // the header is already in the file
+        content = content
+            .split_once('\n')
+            .map(|(_, v)| v.to_string())
+            .unwrap_or_default();
+    }
+    if format == ExportFormat::Ebk && !existing.is_empty() {
+        content = content
+            .strip_prefix(search::export::EBK_HEADER)
+            .map(String::from)
+            .unwrap_or(content);
+    }
+    if existing.is_empty() {
+        // the comment starts the file only
+        content = with_comment(format, content, comment);
+    }
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        content.insert_str(
+            0,
+            if format == ExportFormat::Ebk {
+                "\r\n"
+            } else {
+                "\n"
+            },
+        );
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    file.write_all(&encoding.encode(&content)?)?;
+    eprintln!("appended {} stocks to {}", stocks.len(), path.display());
+    Ok(())
+}
+
+/// Print the exported content to stdout in the encoding.
+fn with_comment(format: ExportFormat, content: String, comment: Option<&str>) -> String {
+    match comment {
+        Some(comment) => format.with_comment(content, comment),
+        None => content,
+    }
+}
+
+/// Print the invalid lines of the block file, fail if any.
+fn validate_block_file(path: &Path) -> color_eyre::Result<()> {
+    let invalid = search::block::check_file(path)?;
+
+    for line in &invalid {
+        eprintln!(
+            "{}:{}: `{}` {}",
+            path.display(),
+            line.line,
+            line.content,
+            line.error
+        );
+    }
+    if invalid.is_empty() {
+        eprintln!("{} is valid", path.display());
+        Ok(())
+    } else {
+        Err(eyre!(
+            "{} invalid lines in {}",
+            invalid.len(),
+            path.display()
+        ))
+    }
+}
+
+fn print_output(content: &str, encoding: OutputEncoding) -> color_eyre::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+
+    stdout.write_all(&encoding.encode(content)?)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Cancel the search on the first Ctrl-C so the resolved stocks are still
+/// written, exit immediately on the second one.
+async fn interrupt(cancel: CancellationToken) {
+    if tokio::signal::ctrl_c().await.is_ok() {
+        eprintln!("interrupted, stop searching, press Ctrl-C again to exit immediately");
+        cancel.cancel();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+async fn serve_on(
+    port: u16,
+    limiter: Option<RateLimiter>,
+    timeouts: Timeouts,
+    headers: HeaderMap,
+) -> color_eyre::Result<()> {
+    let client = client_builder(timeouts, headers).build()?;
+
+    server::serve(port, client, limiter).await
+}
+
+#[cfg(not(feature = "server"))]
+async fn serve_on(
+    _: u16,
+    _: Option<RateLimiter>,
+    _: Timeouts,
+    _: HeaderMap,
+) -> color_eyre::Result<()> {
+    Err(eyre!("The `--serve` mode requires the `server` feature"))
+}
+
+#[cfg(feature = "offline")]
+fn bundled_offline() -> color_eyre::Result<Offline> {
+    Offline::bundled()
+}
+
+#[cfg(not(feature = "offline"))]
+fn bundled_offline() -> color_eyre::Result<Offline> {
+    Err(eyre!(
+        "The `--offline-first` mode requires the `offline` feature"
+    ))
+}
+
 #[derive(Debug)]
 pub struct Searcher {
-    tool: Tool,
+    /// `None` means all the tools, see [`search::all::All`]
+    tool: Option<Tool>,
+    stable: bool,
+    retry_tool: Option<Tool>,
+    retry: RetryConfig,
+    verbose: bool,
+    /// The elapsed time of each request, recorded with `--timings`
+    timings: Option<Vec<(Tool, Duration)>>,
+    timeouts: Timeouts,
+    /// The tool whose name wins in [`search::reconcile_names`]
+    authority: Tool,
+    connect_list: Option<ConnectList>,
+    probe: Probe,
+    /// Cancelled by the first Ctrl-C, see [`interrupt`]
+    cancel: CancellationToken,
     delay: usize,
+    delay_jitter: usize,
+    rng: StdRng,
+    concurrency: usize,
+    /// The concurrency of each tool with `--tool-concurrency`
+    tool_concurrency: HashMap<Tool, usize>,
+    /// The rate limiter of `--global-rps`
+    limiter: Option<RateLimiter>,
+    max_failures: usize,
     keywords: Vec<String>,
+    synonyms: Synonyms,
+    simplify: bool,
+    codes_passthrough: bool,
+    /// The bundled table of `--offline-first`
+    offline: Offline,
+    validate: bool,
+    interactive: bool,
+    enrich: bool,
+    keep_query: bool,
+    unique: bool,
+    all_matches: bool,
+    /// The count of the candidates listed of each keyword with `--explore`
+    explore: Option<usize>,
+    format: ExportFormat,
+    style: NormalizeStyle,
+    output: Option<PathBuf>,
+    encoding: OutputEncoding,
+    append: bool,
+    comment_header: bool,
+    count: bool,
+    strict: bool,
+    on_missing: OnMissing,
+    cookie_store: Option<PathBuf>,
+    /// Write the response bodies to the directory with `--dump-dir`
+    dump_dir: Option<PathBuf>,
+    /// Send the requests to the url with `--base-url`
+    base_url: Option<String>,
+    /// The message of the abort of `--max-failures`, the results before it are kept
+    abort: Option<String>,
+    /// The last error of each failed keyword, recorded with `--report`
+    errors: Option<HashMap<String, SearchError>>,
+    report: Option<PathBuf>,
+    /// The extra headers of `--header` and `--cookie`
+    headers: HeaderMap,
+    options: SearchOptions,
 }
 
 impl Searcher {
-    pub async fn invoke(self) -> color_eyre::Result<()> {
-        let builder = Client::builder()
-            .user_agent(
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:140.0) Gecko/20100101 Firefox/140.0",
-            )
-            .cookie_store(true)
-            .cookie_provider(Jar::default().into());
+    pub async fn invoke(mut self) -> color_eyre::Result<()> {
+        let cookies = self
+            .cookie_store
+            .as_deref()
+            .map(load_cookies)
+            .transpose()?
+            .map(|v| Arc::new(CookieStoreMutex::new(v)));
+        let mut builder = client_builder(self.timeouts, self.headers.clone());
 
-        println!("got keywords count: {}", self.keywords.len());
+        if let Some(cookies) = &cookies {
+            builder = builder.cookie_provider(cookies.clone());
+        }
+        let client = builder.build()?;
+        let keywords = std::mem::take(&mut self.keywords);
+        let total = keywords.len();
+        let start = Instant::now();
+
+        eprintln!("got keywords count: {}", keywords.len());
+
+        let mut results = match self.run(self.tool, client.clone(), keywords.clone()).await {
+            Ok(results) => results,
+            // all the keywords are left to `--retry-tool`
+            Err(e) if self.retry_tool.is_some() => {
+                eprintln!("search failed: {e}");
+                keywords.into_iter().map(|v| (v, vec![])).collect()
+            }
+            Err(e) => return Err(e),
+        };
+
+        if let Some(retry_tool) = self.retry_tool
+            && !self.cancel.is_cancelled()
+        {
+            // switch to the retry tool instead of aborting
+            if let Some(abort) = self.abort.take() {
+                eprintln!("{abort}, switch to {}", retry_tool.name());
+            }
+            let failed: Vec<_> = results
+                .iter()
+                .filter(|(_, stocks)| stocks.is_empty())
+                .map(|(keyword, _)| keyword.clone())
+                .collect();
+
+            if !failed.is_empty() {
+                eprintln!(
+                    "retry {} failed keywords with {}",
+                    failed.len(),
+                    retry_tool.name()
+                );
+
+                let retried = self
+                    .run(Some(retry_tool), client, failed)
+                    .await
+                    .unwrap_or_else(|e| {
+                        eprintln!("retry with {} failed: {e}", retry_tool.name());
+                        vec![]
+                    });
+                let mut failed = results.iter_mut().filter(|(_, stocks)| stocks.is_empty());
+
+                // the keywords not finished at the cancellation are not retried
+                for (keyword, retried) in retried {
+                    if let Some((_, stocks)) = failed.find(|(v, _)| *v == keyword) {
+                        *stocks = retried;
+                    }
+                }
+            }
+        }
+
+        if let Some(timings) = &self.timings {
+            print_timings(timings);
+        }
+        if let (Some(path), Some(cookies)) = (&self.cookie_store, cookies) {
+            save_cookies(path, &cookies)?;
+        }
+        if let Some(path) = &self.report {
+            let report = self.report_of(&results, start.elapsed());
+
+            std::fs::write(path, report.to_json()?)?;
+        }
+        if let Some(top) = self.explore {
+            for (_, stocks) in results.iter_mut() {
+                stocks.truncate(top);
+            }
+            let content = search::export::explore(&results);
+
+            return match &self.output {
+                Some(path) => Ok(std::fs::write(path, self.encoding.encode(&content)?)?),
+                None => print_output(&content, self.encoding),
+            };
+        }
+        let resolved = results.iter().filter(|(_, v)| !v.is_empty()).count();
+
+        if self.on_missing == OnMissing::Error && resolved < results.len() {
+            let missing: Vec<_> = results
+                .iter()
+                .filter(|(_, v)| v.is_empty())
+                .map(|(keyword, _)| keyword.as_str())
+                .collect();
+
+            return Err(eyre!(
+                "{} keywords found nothing: {}",
+                missing.len(),
+                missing.join(", ")
+            ));
+        }
+        // the count of the stocks of each keyword, used by the placeholder lines
+        let groups: Vec<_> = results
+            .iter()
+            .map(|(keyword, v)| (keyword.clone(), v.len()))
+            .collect();
+        let mut stocks: Vec<_> = results.into_iter().flat_map(|(_, v)| v).collect();
+        let conflicts = search::reconcile_names(&mut stocks, self.authority);
 
-        let stocks = match self.tool {
+        if !conflicts.is_empty() {
+            eprintln!("warning: {} codes have different names", conflicts.len());
+        }
+        if self.verbose {
+            for conflict in &conflicts {
+                let names: Vec<_> = conflict
+                    .names
+                    .iter()
+                    .map(|(name, source)| {
+                        format!("{name}({})", source.map(|v| v.name()).unwrap_or("-"))
+                    })
+                    .collect();
+
+                eprintln!(
+                    "conflict {}: {}, use {}",
+                    conflict.normalized,
+                    names.join(" "),
+                    conflict.chosen
+                );
+            }
+        }
+
+        match &self.connect_list {
+            Some(list) => list.apply(&mut stocks),
+            None => {
+                for stock in stocks.iter_mut().filter(|v| v.connect_eligible.is_none()) {
+                    stock.connect_eligible = search::connect::guess_eligible(stock);
+                }
+            }
+        }
+        if self.validate {
+            for stock in &stocks {
+                if let Err(e) = stock.validate() {
+                    eprintln!("warning: {} {e}", stock.name);
+                }
+            }
+        }
+        let stocks = if self.unique {
+            search::dedup_stocks(stocks)
+        } else {
+            stocks
+        };
+
+        if self.cancel.is_cancelled() {
+            eprintln!("interrupted, saved {} resolved stocks", stocks.len());
+        }
+        let comment = self.comment_header.then(|| {
+            search::export::generated_comment(self.tool.map_or("all", |v| v.name()), stocks.len())
+        });
+        let comment = comment.as_deref();
+
+        if self.count {
+            println!("resolved {resolved} of {total}");
+        } else if self.on_missing == OnMissing::Placeholder {
+            let mut stocks = stocks.into_iter();
+            let results: Vec<(String, Vec<Stock>)> = groups
+                .into_iter()
+                .map(|(keyword, count)| (keyword, stocks.by_ref().take(count).collect()))
+                .collect();
+            let content = with_comment(
+                self.format,
+                self.format.export_with_placeholders(&results, self.style)?,
+                comment,
+            );
+
+            match &self.output {
+                Some(path) => std::fs::write(path, self.encoding.encode(&content)?)?,
+                // the lines are already printed
+                None if self.format == ExportFormat::Ndjson => {}
+                None => print_output(&content, self.encoding)?,
+            }
+        } else {
+            match &self.output {
+                Some(path) => write_output(
+                    path,
+                    self.format,
+                    self.style,
+                    self.encoding,
+                    stocks,
+                    self.append,
+                    comment,
+                )?,
+                // the lines are already printed
+                None if self.format == ExportFormat::Ndjson => {}
+                None => print_output(
+                    &with_comment(
+                        self.format,
+                        self.format.export_with(&stocks, self.style)?,
+                        comment,
+                    ),
+                    self.encoding,
+                )?,
+            }
+        }
+        if let Some(abort) = &self.abort {
+            return Err(eyre!("{abort}"));
+        }
+        if self.strict && resolved < total {
+            return Err(eyre!("{} of {total} keywords failed", total - resolved));
+        }
+        Ok(())
+    }
+
+    /// The summary of the results, the failed keywords without a recorded error
+    /// are the ones found nothing.
+    fn report_of(&self, results: &[(String, Vec<Stock>)], elapsed: Duration) -> BatchReport {
+        let mut report = BatchReport {
+            elapsed,
+            ..Default::default()
+        };
+
+        for (keyword, stocks) in results {
+            if stocks.is_empty() {
+                let error = self
+                    .errors
+                    .as_ref()
+                    .and_then(|v| v.get(keyword))
+                    .cloned()
+                    .unwrap_or_else(|| SearchError::new("found nothing"));
+
+                report.failed.push((keyword.clone(), error));
+            }
+            for stock in stocks {
+                report.resolved.push((keyword.clone(), stock.clone()));
+            }
+        }
+        report
+    }
+
+    /// Search the keywords with the tool, the failed keywords are kept with an
+    /// empty list. Only one stock is kept for each keyword without `--all-matches`.
+    pub async fn run(
+        &mut self,
+        tool: Option<Tool>,
+        client: Client,
+        keywords: Vec<String>,
+    ) -> color_eyre::Result<Vec<(String, Vec<Stock>)>> {
+        let hk = matches!(self.options.exchange_hint, Some(Exchange::HongKong));
+        let base_url = self.base_url.clone();
+        let base_url = base_url.as_deref();
+        let Some(tool) = tool else {
+            let tool = search::all::All::init_with_probe(client, &self.probe, &Tool::ALL)
+                .await?
+                .with_stable(self.stable)
+                .with_limiter(self.limiter.clone())
+                .with_tool_concurrency(&self.tool_concurrency)
+                .with_dump_dir(self.dump_dir.clone());
+            let input = search::all::Input::default().with_hk(hk);
+
+            return self.search_with(&tool, input, None, keywords).await;
+        };
+
+        match tool {
             Tool::CnInfo => {
-                let tool = cninfo::CnInfo::init(builder).await?;
+                let tool = cninfo::CnInfo::init_with_probe(client, &self.probe)
+                    .await?
+                    .with_base_url(base_url.unwrap_or(cninfo::BASE_URL))
+                    .with_dump_dir(self.dump_dir.clone());
+                let input = cninfo::Input::default().with_hk(hk);
 
-                self.search(&tool).await?
+                self.search_with(&tool, input, Some(Tool::CnInfo), keywords)
+                    .await
             }
             Tool::Sina => {
-                let tool = sina::Sina::init(builder).await?;
+                let tool = sina::Sina::init_with_probe(client, &self.probe)
+                    .await?
+                    .with_base_url(base_url.unwrap_or(sina::BASE_URL))
+                    .with_dump_dir(self.dump_dir.clone());
 
-                self.search(&tool).await?
+                self.search(&tool, Tool::Sina, keywords).await
             }
             Tool::Cfi => {
-                let tool = cfi::Cfi::init(builder).await?;
+                let tool = cfi::Cfi::init_with_probe(client, &self.probe)
+                    .await?
+                    .with_base_url(base_url.unwrap_or(cfi::BASE_URL))
+                    .with_dump_dir(self.dump_dir.clone());
 
-                self.search(&tool).await?
+                self.search(&tool, Tool::Cfi, keywords).await
             }
             Tool::HeXun => {
-                let tool = hexun::Hexun::init(builder).await?;
+                let tool = hexun::Hexun::init_with_probe(client, &self.probe)
+                    .await?
+                    .with_base_url(base_url.unwrap_or(hexun::BASE_URL))
+                    .with_dump_dir(self.dump_dir.clone());
 
-                self.search(&tool).await?
+                self.search(&tool, Tool::HeXun, keywords).await
             }
             Tool::SoHu => {
-                let tool = sohu::SoHu::init(builder).await?;
+                let tool = sohu::SoHu::init_with_probe(client, &self.probe)
+                    .await?
+                    .with_base_url(base_url.unwrap_or(sohu::BASE_URL))
+                    .with_dump_dir(self.dump_dir.clone());
 
-                self.search(&tool).await?
+                self.search(&tool, Tool::SoHu, keywords).await
             }
-        };
-
-        for stock in stocks {
-            println!("{}", stock.normalize());
         }
-        Ok(())
     }
 
-    pub async fn search<T>(self, tool: &T) -> color_eyre::Result<Vec<Stock>>
+    pub async fn search<T>(
+        &mut self,
+        tool: &T,
+        name: Tool,
+        keywords: Vec<String>,
+    ) -> color_eyre::Result<Vec<(String, Vec<Stock>)>>
     where
         T: Search,
         T::Input: Clone + Default,
     {
-        self.search_with(tool, <T::Input>::default()).await
+        self.search_with(tool, <T::Input>::default(), Some(name), keywords)
+            .await
     }
 
     pub async fn search_with<T>(
-        self,
+        &mut self,
         tool: &T,
-        mut input: T::Input,
-    ) -> color_eyre::Result<Vec<Stock>>
+        input: T::Input,
+        name: Option<Tool>,
+        keywords: Vec<String>,
+    ) -> color_eyre::Result<Vec<(String, Vec<Stock>)>>
     where
         T: Search,
         T::Input: Clone,
     {
-        let mut stocks = vec![];
+        if let Err(e) = tool.warm_up().await {
+            eprintln!("warm up {} failed: {e}", name.map_or("all", |v| v.name()));
+        }
 
-        for keyword in self.keywords {
-            println!("try to search {keyword}",);
+        let batch = Batch::default()
+            .with_options(self.options.clone())
+            .with_simplify(self.simplify)
+            .with_passthrough(self.codes_passthrough)
+            .with_offline(self.offline.clone())
+            .with_concurrency(
+                name.and_then(|v| self.tool_concurrency.get(&v))
+                    .map_or(self.concurrency, |v| self.concurrency.min(*v)),
+            )
+            // the aggregated sources take the tokens by themselves
+            .with_limiter(self.limiter.clone().filter(|_| name.is_some()))
+            .with_synonyms(self.synonyms.clone())
+            .with_enrich(self.enrich)
+            // the keyword of each candidate is kept to tell the groups apart
+            .with_keep_query(self.keep_query || self.all_matches)
+            .with_source(name)
+            .with_retry(self.retry)
+            .with_cancel(Some(self.cancel.clone()));
+        let mut reporter = CliReporter {
+            name,
+            stream: self.format == ExportFormat::Ndjson
+                && self.output.is_none()
+                && !self.count
+                && self.explore.is_none(),
+            verbose: self.verbose,
+            interactive: self.interactive && atty::is(atty::Stream::Stdin),
+            max_failures: self.max_failures,
+            failures: 0,
+            abort: None,
+            delay: self.delay,
+            delay_jitter: self.delay_jitter,
+            rng: &mut self.rng,
+            timings: self.timings.as_mut(),
+            errors: self.errors.as_mut(),
+        };
+        let searched = keywords.clone();
+        let mut results = if self.all_matches {
+            batch.run_all(tool, input, keywords, &mut reporter).await
+        } else {
+            batch
+                .run(tool, input, keywords, &mut reporter)
+                .await
+                .into_iter()
+                .map(|(keyword, stock)| (keyword, stock.into_iter().collect()))
+                .collect()
+        };
 
-            stocks.push(
-                tool.search({
-                    input.reset();
-                    input.set_keyword(keyword);
-                    &input
+        // the results before the abort are still written, the keywords not
+        // searched are kept as failed so `--retry-tool` can search them
+        if let Some(abort) = reporter.abort {
+            let mut finished = results.into_iter().peekable();
+
+            results = searched
+                .into_iter()
+                .map(|keyword| match finished.next_if(|(v, _)| *v == keyword) {
+                    Some(result) => result,
+                    None => {
+                        if let Some(errors) = &mut self.errors {
+                            errors
+                                .entry(keyword.clone())
+                                .or_insert_with(|| SearchError::new(abort.clone()));
+                        }
+                        (keyword, vec![])
+                    }
                 })
-                .await?,
-            );
+                .collect();
+            self.abort = Some(abort);
+        }
+        Ok(results)
+    }
+}
+
+/// Print the progress to stderr, and abort after too many consecutive failures.
+struct CliReporter<'a> {
+    /// `None` for all the tools, the source of each stock is used
+    name: Option<Tool>,
+    /// Print the ndjson lines to stdout as each keyword resolves
+    stream: bool,
+    verbose: bool,
+    interactive: bool,
+    max_failures: usize,
+    failures: usize,
+    abort: Option<String>,
+    delay: usize,
+    delay_jitter: usize,
+    rng: &'a mut StdRng,
+    timings: Option<&'a mut Vec<(Tool, Duration)>>,
+    errors: Option<&'a mut HashMap<String, SearchError>>,
+}
+
+impl Reporter for CliReporter<'_> {
+    fn report(&mut self, progress: Progress<'_>) -> ControlFlow<()> {
+        match progress {
+            Progress::Started { keyword, .. } => {
+                eprintln!("try to search {keyword}");
+            }
+            Progress::Resolved {
+                keyword,
+                stock,
+                elapsed,
+                ..
+            } => {
+                self.failures = 0;
+                if let (Some(timings), Some(name)) = (&mut self.timings, self.name.or(stock.source))
+                {
+                    timings.push((name, elapsed));
+                }
+                if self.stream {
+                    match search::export::ndjson_line(stock) {
+                        Ok(line) => println!("{line}"),
+                        Err(e) => eprintln!("can not print {keyword}: {e}"),
+                    }
+                    let _ = std::io::stdout().flush();
+                }
+                if self.verbose {
+                    eprintln!(
+                        "resolved {keyword} to {} by {}, confidence {}",
+                        stock.code,
+                        self.name.or(stock.source).map_or("all", |v| v.name()),
+                        stock.confidence.map_or("unknown", |v| v.name())
+                    );
+                }
+            }
+            Progress::Failed {
+                keyword,
+                error,
+                elapsed,
+                missing,
+                ..
+            } => {
+                // the source answered, it is not an outage
+                if missing {
+                    self.failures = 0;
+                } else {
+                    self.failures += 1;
+                }
+                if let (Some(timings), Some(name)) = (&mut self.timings, self.name) {
+                    timings.push((name, elapsed));
+                }
+                if let Some(errors) = &mut self.errors {
+                    // the error of the retry replaces the first one
+                    errors.insert(keyword.to_string(), SearchError::from(error));
+                }
+                if self.stream {
+                    println!("{}", search::export::ndjson_error(keyword, error));
+                    let _ = std::io::stdout().flush();
+                }
+                eprintln!("search {keyword} failed: {error}");
+                if self.max_failures > 0 && self.failures >= self.max_failures {
+                    self.abort = Some(format!(
+                        "Abort searching after {} consecutive failures: {error}",
+                        self.failures
+                    ));
+                    return ControlFlow::Break(());
+                }
+            }
+            Progress::EnrichFailed { keyword, error, .. } => {
+                eprintln!("enrich {keyword} failed: {error}");
+            }
+            Progress::Begin { .. } | Progress::Done { .. } => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn select(
+        &mut self,
+        keyword: &str,
+        candidates: Vec<Stock>,
+        options: &SearchOptions,
+    ) -> color_eyre::Result<Stock> {
+        if self.interactive {
+            pick(keyword, candidates, options)
+        } else {
+            options.select_or_err(candidates)
+        }
+    }
+
+    fn delay(&mut self) -> Duration {
+        let jitter = self.rng.random_range(0..=self.delay_jitter);
+
+        Duration::from_millis((self.delay + jitter) as u64)
+    }
+}
 
-            sleep(Duration::from_millis(self.delay as u64)).await;
+/// Ask the user to pick one of the candidates, an empty answer use the default selection.
+fn pick(
+    keyword: &str,
+    mut candidates: Vec<Stock>,
+    options: &SearchOptions,
+) -> color_eyre::Result<Stock> {
+    let count = candidates.len();
+
+    if count > 1 {
+        eprintln!("found {count} candidates of {keyword}:");
+        for (index, stock) in candidates.iter().enumerate() {
+            eprintln!(
+                "  {}) {} {} {:?}",
+                index + 1,
+                stock.name,
+                stock.normalize(),
+                stock.exchange
+            );
         }
+        loop {
+            let mut line = String::default();
+
+            eprint!("select [1-{count}], empty for default: ");
+            std::io::stderr().flush()?;
+            std::io::stdin().read_line(&mut line)?;
 
-        Ok(stocks)
+            let line = line.trim();
+
+            if line.is_empty() {
+                break;
+            }
+            if let Ok(index) = line.parse::<usize>()
+                && (1..=count).contains(&index)
+            {
+                return Ok(candidates.swap_remove(index - 1));
+            }
+            eprintln!("invalid selection `{line}`");
+        }
     }
+
+    options.select_or_err(candidates)
 }
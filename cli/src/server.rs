@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use reqwest::Client;
+use search::limiter::RateLimiter;
+use search::{QueryInput, Search, SearchOptions, Stock, Tool, cfi, cninfo, hexun, sina, sohu};
+use tokio::sync::OnceCell;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct Params {
+    name: String,
+    tool: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ErrorBody {
+    error: String,
+}
+
+/// The failures of [`Server::resolve`], each is answered with its own status.
+#[derive(Debug)]
+pub enum ResolveError {
+    /// The source can not be initialized, such as its homepage is unreachable
+    Init(color_eyre::Report),
+    /// The request of the source failed, or the response can not be parsed
+    Upstream(color_eyre::Report),
+    /// The source has no stock of the name
+    NotFound(color_eyre::Report),
+}
+
+impl ResolveError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ResolveError::Init(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ResolveError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            ResolveError::NotFound(_) => StatusCode::NOT_FOUND,
+        }
+    }
+
+    pub fn into_report(self) -> color_eyre::Report {
+        match self {
+            ResolveError::Init(e) | ResolveError::Upstream(e) | ResolveError::NotFound(e) => e,
+        }
+    }
+}
+
+/// The sources are initialized on first use and reused by later requests.
+#[derive(Debug)]
+pub struct Server {
+    client: Client,
+    /// Shared by the requests, each upstream request takes a token
+    limiter: Option<RateLimiter>,
+    cninfo: OnceCell<cninfo::CnInfo>,
+    sina: OnceCell<sina::Sina>,
+    cfi: OnceCell<cfi::Cfi>,
+    hexun: OnceCell<hexun::Hexun>,
+    sohu: OnceCell<sohu::SoHu>,
+}
+
+impl Server {
+    pub fn new(client: Client, limiter: Option<RateLimiter>) -> Self {
+        Self {
+            client,
+            limiter,
+            cninfo: OnceCell::new(),
+            sina: OnceCell::new(),
+            cfi: OnceCell::new(),
+            hexun: OnceCell::new(),
+            sohu: OnceCell::new(),
+        }
+    }
+
+    /// Wait for the [`Server::limiter`] before an upstream request.
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire().await;
+        }
+    }
+
+    pub async fn resolve(&self, tool: Tool, keyword: String) -> Result<Stock, ResolveError> {
+        let mut stock = self.resolve_with(tool, keyword).await?;
+
+        stock.source = Some(tool);
+        Ok(stock)
+    }
+
+    async fn resolve_with(&self, tool: Tool, keyword: String) -> Result<Stock, ResolveError> {
+        let client = || self.client.clone();
+
+        self.throttle().await;
+        match tool {
+            Tool::CnInfo => {
+                let tool = self
+                    .cninfo
+                    .get_or_try_init(|| warmed(cninfo::CnInfo::init_with_client(client())))
+                    .await
+                    .map_err(ResolveError::Init)?;
+
+                search_one(tool, keyword).await
+            }
+            Tool::Sina => {
+                let tool = self
+                    .sina
+                    .get_or_try_init(|| warmed(sina::Sina::init_with_client(client())))
+                    .await
+                    .map_err(ResolveError::Init)?;
+
+                search_one(tool, keyword).await
+            }
+            Tool::Cfi => {
+                let tool = self
+                    .cfi
+                    .get_or_try_init(|| warmed(cfi::Cfi::init_with_client(client())))
+                    .await
+                    .map_err(ResolveError::Init)?;
+
+                search_one(tool, keyword).await
+            }
+            Tool::HeXun => {
+                let tool = self
+                    .hexun
+                    .get_or_try_init(|| warmed(hexun::Hexun::init_with_client(client())))
+                    .await
+                    .map_err(ResolveError::Init)?;
+
+                search_one(tool, keyword).await
+            }
+            Tool::SoHu => {
+                let tool = self
+                    .sohu
+                    .get_or_try_init(|| warmed(sohu::SoHu::init_with_client(client())))
+                    .await
+                    .map_err(ResolveError::Init)?;
+
+                search_one(tool, keyword).await
+            }
+        }
+    }
+}
+
+/// Initialize the source and prime its session once.
+async fn warmed<T: Search>(
+    init: impl Future<Output = color_eyre::Result<T>>,
+) -> color_eyre::Result<T> {
+    let tool = init.await?;
+
+    tool.warm_up().await?;
+    Ok(tool)
+}
+
+/// Search the candidates, then select one, the failure of the request is told
+/// apart from the name found nothing.
+async fn search_one<T>(tool: &T, keyword: String) -> Result<Stock, ResolveError>
+where
+    T: Search,
+    T::Input: Default,
+{
+    let options = SearchOptions::default();
+    let mut input = <T::Input>::default();
+
+    input.set_keyword(keyword);
+
+    let candidates = tool
+        .search_candidates(&input, &options)
+        .await
+        .map_err(ResolveError::Upstream)?;
+
+    options
+        .select_or_err(candidates)
+        .map_err(ResolveError::NotFound)
+}
+
+fn error(status: StatusCode, error: color_eyre::Report) -> Response {
+    let body = ErrorBody {
+        error: error.to_string(),
+    };
+
+    (status, Json(body)).into_response()
+}
+
+async fn resolve(State(server): State<Arc<Server>>, Query(params): Query<Params>) -> Response {
+    let tool = match params.tool.as_deref().map(str::parse::<Tool>).transpose() {
+        Ok(tool) => tool.unwrap_or_default(),
+        Err(e) => return error(StatusCode::BAD_REQUEST, e),
+    };
+
+    match server.resolve(tool, params.name).await {
+        Ok(stock) => Json(stock).into_response(),
+        Err(e) => error(e.status(), e.into_report()),
+    }
+}
+
+pub async fn serve(
+    port: u16,
+    client: Client,
+    limiter: Option<RateLimiter>,
+) -> color_eyre::Result<()> {
+    let server = Arc::new(Server::new(client, limiter));
+    let app = Router::new()
+        .route("/resolve", get(resolve))
+        .with_state(server);
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+
+    eprintln!("listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use std::process::{Command, Stdio};
+
+/// Inspect the keywords with the arguments, no request is sent.
+fn inspect(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .arg("--inspect")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn expand_bundled_aliases_by_default() {
+    assert!(inspect(&["茅台"]).contains("expanded: 茅台 -> 贵州茅台"));
+}
+
+#[test]
+fn expand_aliases_of_the_file_over_bundled() {
+    let path =
+        std::env::temp_dir().join(format!("stockconverter-aliases-{}.csv", std::process::id()));
+
+    std::fs::write(&path, "平银,平安银行\n").unwrap();
+
+    let stdout = inspect(&["--aliases", path.to_str().unwrap(), "茅台", "平银"]);
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(stdout.contains("expanded: 茅台 -> 贵州茅台"), "{stdout}");
+    assert!(stdout.contains("expanded: 平银 -> 平安银行"), "{stdout}");
+}
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+/// Write the block files to a new directory named by the test.
+fn block_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("stockconverter-{name}-{}", std::process::id()));
+
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, content) in files {
+        std::fs::write(dir.join(file), content).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn merge_block_files() {
+    let dir = block_files(
+        "cli-merge",
+        &[
+            ("first.ebk", "\r\n1600519\r\n0000001\r\n"),
+            ("second.blk", "sz000001\n00700.HK\n"),
+        ],
+    );
+    let first = dir.join("first.ebk");
+    let second = dir.join("second.blk");
+    let merged = dir.join("merged.ebk");
+    let output = run(&[
+        "merge",
+        first.to_str().unwrap(),
+        second.to_str().unwrap(),
+        "-o",
+        merged.to_str().unwrap(),
+    ]);
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        std::fs::read_to_string(&merged).unwrap(),
+        "\r\n1600519\r\n0000001\r\n500700\r\n"
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn validate_block_file() {
+    let dir = block_files(
+        "cli-validate",
+        &[
+            ("valid.ebk", "\r\n1600519\r\n500700\r\n"),
+            ("invalid.ebk", "\r\n1600519\r\n0600519\r\nabc\r\n"),
+        ],
+    );
+    let valid = run(&["validate", dir.join("valid.ebk").to_str().unwrap()]);
+
+    assert!(valid.status.success(), "{valid:?}");
+
+    let invalid = run(&["validate", dir.join("invalid.ebk").to_str().unwrap()]);
+    let stderr = String::from_utf8_lossy(&invalid.stderr);
+
+    assert!(!invalid.status.success());
+    assert!(stderr.contains("invalid.ebk:3: `0600519`"), "{stderr}");
+    assert!(stderr.contains("invalid.ebk:4: `abc`"), "{stderr}");
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,88 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::process::{Command, Output, Stdio};
+
+/// Serve the bodies in order, one per connection, return the base url.
+fn serve(bodies: Vec<&'static str>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for (body, stream) in bodies.into_iter().zip(listener.incoming()) {
+            let mut stream = stream.unwrap();
+            let mut buff = [0; 4096];
+            let _ = stream.read(&mut buff);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    });
+    format!("http://{addr}")
+}
+
+const SINA: &str = include_str!("../../search/tests/fixtures/sina.txt");
+
+/// Search the keywords with the mock sina serving the bodies.
+fn search(bodies: Vec<&'static str>, args: &[&str], keywords: &[&str]) -> Output {
+    let base_url = serve(bodies);
+
+    Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["--tool", "sina", "--no-probe", "--delay", "0", "--base-url"])
+        .arg(&base_url)
+        .args(args)
+        .args(keywords)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+/// Search the missing keyword then 贵州茅台.
+fn search_missing(args: &[&str]) -> String {
+    let output = search(
+        vec!["var suggestdata_1=\"\";", SINA],
+        args,
+        &["不存在", "贵州茅台"],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn skip_the_missing_keyword() {
+    assert_eq!(search_missing(&[]), "\r\n1600519\r\n");
+}
+
+#[test]
+fn placeholder_of_the_missing_keyword() {
+    assert_eq!(
+        search_missing(&["--on-missing", "placeholder"]),
+        "\r\n# 不存在: not found\r\n1600519\r\n"
+    );
+}
+
+#[test]
+fn abort_keeps_the_resolved() {
+    let output = search(vec![SINA, "broken"], &[], &["贵州茅台", "茅台", "贵州茅台"]);
+
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\r\n1600519\r\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Abort searching"));
+}
+
+#[test]
+fn abort_switch_to_the_retry_tool() {
+    let output = search(
+        vec!["broken", SINA, SINA],
+        &["--retry-tool", "sina"],
+        &["茅台", "贵州茅台"],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\r\n1600519\r\n1600519\r\n"
+    );
+}
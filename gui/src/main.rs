@@ -3,7 +3,7 @@
     windows_subsystem = "windows"
 )]
 
-use std::{fmt::Debug, time::Duration};
+use std::{collections::VecDeque, fmt::Debug, ops::ControlFlow, path::PathBuf, time::Duration};
 
 use iced::widget::text;
 use iced::{
@@ -11,22 +11,33 @@ use iced::{
     futures::{SinkExt, Stream, channel::mpsc::Sender},
     task::Handle,
     widget::{
-        button, column, container, horizontal_rule, radio, row, slider, text_editor, text_input,
+        Column, button, checkbox, column, container, horizontal_rule, pick_list, radio, row,
+        scrollable, slider, text_editor, text_input,
     },
     window::{Settings, icon},
     *,
 };
 
+use rand::Rng;
 use reqwest::{Client, cookie::Jar};
+use search::NormalizeStyle;
+use search::Stock;
+use search::batch::{Batch, Progress, Reporter};
 use search::cninfo;
+use search::export::ExportFormat;
 use search::hexun;
 use search::sina;
-use search::{QueryInput, Stock};
 use search::{Search, cfi};
 use search::{Tool, sohu};
 
 const APP_PNG: &[u8] = include_bytes!("../app.png");
 
+/// The max lines kept in the log, the oldest lines are dropped.
+const LOG_LINES: usize = 500;
+
+/// The file name of the settings, saved next to the executable.
+const SETTINGS_FILE: &str = "stock-convert.json";
+
 pub fn main() -> iced::Result {
     iced::application(Gui::new, Gui::update, Gui::view)
         .title("股票简称转代码")
@@ -45,12 +56,19 @@ pub fn main() -> iced::Result {
 #[derive(Debug, Default)]
 pub struct Gui {
     delay: f64,
+    jitter: f64,
     path: String,
+    export_format: ExportFormat,
+    style: NormalizeStyle,
+    /// Start the exported file with the generated comment
+    comment_header: bool,
     input: text_editor::Content,
     tool_sel: Option<Tool>,
-    infobar: String,
+    log: VecDeque<String>,
     output: text_editor::Content,
     content: String,
+    /// The reports in order, the output is rendered from them in the style
+    reports: Vec<TaskReport>,
     stocks: Vec<Stock>,
     keyword_index: usize,
     keyword_total: usize,
@@ -64,11 +82,16 @@ pub enum Message {
     OutputAct(text_editor::Action),
     ToolSel(Tool),
     SetDelay(f64),
+    SetJitter(f64),
     SetPath(String),
+    ExportFormatSel(ExportFormat),
+    StyleSel(NormalizeStyle),
+    CommentHeaderToggled(bool),
     SetInfobar(String),
     StartTask,
     StopTask,
     CleanOutput,
+    CleanLog,
     TaskReport(TaskReport),
     TaskFinished(bool),
     ExportResult,
@@ -82,14 +105,21 @@ pub enum TaskReport {
 
 impl Gui {
     pub fn new() -> Self {
+        let settings = GuiSettings::load();
+
         Self {
             delay: 1.0,
+            jitter: 0.0,
             path: String::default(),
+            export_format: ExportFormat::default(),
+            style: settings.style(),
+            comment_header: false,
             tool_sel: Some(Tool::CnInfo),
             input: text_editor::Content::default(),
             output: text_editor::Content::default(),
-            infobar: String::default(),
+            log: VecDeque::default(),
             content: String::default(),
+            reports: vec![],
             keyword_index: 0,
             keyword_total: 0,
             task_handle: None,
@@ -101,6 +131,40 @@ impl Gui {
         (self.delay * 50.) as _
     }
 
+    pub fn task_jitter(&self) -> u64 {
+        (self.jitter * 50.) as _
+    }
+
+    pub fn default_path(&self) -> String {
+        format!("output.{}", self.export_format.extension())
+    }
+
+    /// Render the output line of the report in the style.
+    pub fn output_line(&self, report: &TaskReport) -> String {
+        match report {
+            TaskReport::Failed((keyword, _)) => format!("{keyword}: 无可用的结果\n"),
+            TaskReport::Stock(stock) => {
+                format!("{} ==> {}\n", stock.name, stock.normalize_with(self.style))
+            }
+        }
+    }
+
+    /// Render the output again from the reports, such as the style is changed.
+    pub fn render_output(&mut self) {
+        self.content = self.reports.iter().map(|v| self.output_line(v)).collect();
+        self.output = text_editor::Content::with_text(&self.content);
+    }
+
+    /// Append a timestamped line to the log.
+    pub fn push_log(&mut self, line: String) {
+        let now = chrono::Local::now().format("%H:%M:%S");
+
+        self.log.push_back(format!("[{now}] {line}"));
+        while self.log.len() > LOG_LINES {
+            self.log.pop_front();
+        }
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::Nothing => {}
@@ -116,77 +180,104 @@ impl Gui {
             Message::SetDelay(value) => {
                 self.delay = value;
             }
+            Message::SetJitter(value) => {
+                self.jitter = value;
+            }
             Message::ExportResult => {
                 let path = if self.path.is_empty() {
-                    "output.ebk"
+                    self.default_path()
                 } else {
-                    self.path.as_ref()
+                    self.path.clone()
                 };
-                let path = path.to_string();
-                let mut content = String::default();
 
                 if !self.stocks.is_empty() {
-                    for stock in &self.stocks {
-                        content.push_str(&stock.normalize());
-                        content.push('\n');
-                    }
-                    return Task::future(async move {
-                        if let Err(e) = tokio::fs::write(path, content).await {
-                            Message::SetInfobar(format!("写入文件错误: {e:?}"))
-                        } else {
-                            Message::Nothing
+                    match self.export_format.export_with(&self.stocks, self.style) {
+                        Ok(mut content) => {
+                            if self.comment_header {
+                                let comment = search::export::generated_comment(
+                                    self.tool_sel.map_or("all", |v| v.name()),
+                                    self.stocks.len(),
+                                );
+
+                                content = self.export_format.with_comment(content, &comment);
+                            }
+                            return Task::future(write_export(path, self.default_path(), content));
                         }
-                    });
+                        Err(e) => {
+                            self.push_log(format!("导出错误: {e:?}"));
+                        }
+                    }
                 }
             }
             Message::SetPath(path) => {
                 self.path = path;
             }
+            Message::ExportFormatSel(format) => {
+                self.export_format = format;
+            }
+            Message::CommentHeaderToggled(comment_header) => {
+                self.comment_header = comment_header;
+            }
+            Message::StyleSel(style) => {
+                self.style = style;
+                self.render_output();
+                if let Err(e) = (GuiSettings {
+                    style: style.name().to_string(),
+                })
+                .save()
+                {
+                    self.push_log(format!("保存设置错误: {e:?}"));
+                }
+            }
             Message::TaskFinished(_) => {
                 self.task_handle = None;
             }
             Message::CleanOutput => {
                 self.stocks.clear();
-                self.content.clear();
-                self.output = text_editor::Content::with_text(&self.content);
+                self.reports.clear();
+                self.render_output();
+            }
+            Message::CleanLog => {
+                self.log.clear();
             }
             Message::TaskReport(report) => {
                 self.keyword_index += 1;
-                match report {
+                match &report {
                     TaskReport::Failed((keyword, msg)) => {
-                        self.infobar = format!("搜索关键字 `{keyword}` 失败: {msg}");
-                        self.content.push_str(&format!("{keyword}: 无可用的结果\n"));
-                        self.output = text_editor::Content::with_text(&self.content);
+                        self.push_log(format!("搜索关键字 `{keyword}` 失败: {msg}"));
                     }
                     TaskReport::Stock(stock) => {
-                        self.content
-                            .push_str(&format!("{} ==> {}\n", stock.name, stock.code));
-                        self.output = text_editor::Content::with_text(&self.content);
-                        self.stocks.push(stock);
+                        self.stocks.push(stock.clone());
                     }
                 }
+                let line = self.output_line(&report);
+
+                self.content.push_str(&line);
+                self.output = text_editor::Content::with_text(&self.content);
+                self.reports.push(report);
             }
             Message::SetInfobar(value) => {
-                self.infobar = value;
+                self.push_log(value);
             }
             Message::StartTask => {
                 if self.task_handle.is_none() {
                     let tool = self.tool_sel.unwrap_or_default();
                     let delay = self.task_delay();
+                    let jitter = self.task_jitter();
                     let keywords: Vec<String> = self
                         .input
                         .lines()
-                        .map(|v| v.text.trim().to_string())
-                        .filter(|v| !v.is_empty())
+                        .flat_map(|v| search::split_keywords(&v.text, search::SPLIT_CHARS))
                         .collect();
 
                     self.content.clear();
+                    self.reports.clear();
                     self.stocks.clear();
                     self.keyword_total = keywords.len();
                     self.keyword_index = 0;
 
                     let (task, handle) =
-                        Task::stream(start_task(tool, keywords, delay)).abortable();
+                        Task::stream(start_task(tool, keywords, delay, jitter)).abortable();
 
                     self.task_handle = Some(handle.abort_on_drop());
 
@@ -238,6 +329,13 @@ impl Gui {
         .spacing(5)
         .align_y(Vertical::Center);
 
+        let jitter = row![
+            slider(0.0..=50.0, self.jitter, Message::SetJitter),
+            text(format!("抖动: {}毫秒", self.task_jitter())),
+        ]
+        .spacing(5)
+        .align_y(Vertical::Center);
+
         let start = button("搜索").on_press_maybe(if self.task_handle.is_some() {
             None
         } else {
@@ -247,7 +345,18 @@ impl Gui {
         let stop =
             button("停止").on_press_maybe(self.task_handle.as_ref().map(|_| Message::StopTask));
 
-        let path = text_input("output.ebk", &self.path).on_input(Message::SetPath);
+        let path = text_input(&self.default_path(), &self.path).on_input(Message::SetPath);
+
+        let format = pick_list(
+            ExportFormat::ALL,
+            Some(self.export_format),
+            Message::ExportFormatSel,
+        );
+
+        let style = pick_list(NormalizeStyle::ALL, Some(self.style), Message::StyleSel);
+
+        let comment_header =
+            checkbox("注释头", self.comment_header).on_toggle(Message::CommentHeaderToggled);
 
         let export = button("导出").on_press_maybe(if self.content.is_empty() {
             None
@@ -255,15 +364,41 @@ impl Gui {
             Some(Message::ExportResult)
         });
 
-        let operators = row![delay, start, stop, path, export]
-            .spacing(5)
-            .padding(5)
-            .height(Length::FillPortion(1)); //.height(Length::Fixed(80.));
+        let clean_log = button("清空日志").on_press_maybe(if self.log.is_empty() {
+            None
+        } else {
+            Some(Message::CleanLog)
+        });
 
-        let mut infobar = text_input("状态栏", &self.infobar)
-            .size(Pixels::from(10.0))
+        let operators = row![
+            delay,
+            jitter,
+            start,
+            stop,
+            path,
+            format,
+            style,
+            comment_header,
+            export,
+            clean_log
+        ]
+        .spacing(5)
+        .padding(5)
+        .height(Length::FillPortion(1)); //.height(Length::Fixed(80.));
+
+        let log = Column::with_children(
+            self.log
+                .iter()
+                .map(|v| text(v).size(Pixels::from(10.0)).into()),
+        )
+        .width(Length::Fill);
+
+        let mut infobar = container(scrollable(log).anchor_bottom().width(Length::Fill))
+            .padding(5)
             .align_x(Horizontal::Left)
-            .width(Length::Fill);
+            .width(Length::Fill)
+            .height(Length::FillPortion(2))
+            .style(container::bordered_box);
 
         if self.task_handle.is_some() {
             let task_progress = if self.keyword_total > 0 {
@@ -299,6 +434,40 @@ impl Gui {
     }
 }
 
+/// The settings kept between runs.
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+pub struct GuiSettings {
+    /// The name of the [`NormalizeStyle`]
+    #[serde(default)]
+    pub style: String,
+}
+
+impl GuiSettings {
+    pub fn path() -> Option<PathBuf> {
+        std::env::current_exe()
+            .ok()
+            .map(|v| v.with_file_name(SETTINGS_FILE))
+    }
+
+    /// Load the settings, the default is used if the file is missing or broken.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|v| std::fs::read_to_string(v).ok())
+            .and_then(|v| serde_json::from_str(&v).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = Self::path().ok_or_else(|| std::io::Error::other("no executable path"))?;
+
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn style(&self) -> NormalizeStyle {
+        self.style.parse().unwrap_or_default()
+    }
+}
+
 pub async fn try_unwrap<T, E: Debug>(
     t: std::result::Result<T, E>,
     send: &mut Sender<Message>,
@@ -318,23 +487,23 @@ pub fn start_task(
     tool: Tool,
     keywords: Vec<String>,
     delay: u64,
+    jitter: u64,
 ) -> impl Stream<Item = Message> + 'static {
     iced::stream::channel(1024, async move |mut send| {
-        let builder = Client::builder()
-            .user_agent(
-                "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:140.0) Gecko/20100101 Firefox/140.0",
-            )
+        let builder = search::decompression(Client::builder())
+            .user_agent(search::USER_AGENT)
             .cookie_store(true)
             .cookie_provider(Jar::default().into());
 
         let mut success = false;
+        let source = tool;
 
         match tool {
             Tool::CnInfo => {
                 let tool = cninfo::CnInfo::init(builder).await;
 
                 if let Some(tool) = try_unwrap(tool, &mut send).await {
-                    process(tool, keywords, &mut send, delay).await;
+                    process(tool, source, keywords, &mut send, delay, jitter).await;
                     success = true;
                 }
             }
@@ -342,7 +511,7 @@ pub fn start_task(
                 let tool = sina::Sina::init(builder).await;
 
                 if let Some(tool) = try_unwrap(tool, &mut send).await {
-                    process(tool, keywords, &mut send, delay).await;
+                    process(tool, source, keywords, &mut send, delay, jitter).await;
                     success = true;
                 }
             }
@@ -350,7 +519,7 @@ pub fn start_task(
                 let tool = cfi::Cfi::init(builder).await;
 
                 if let Some(tool) = try_unwrap(tool, &mut send).await {
-                    process(tool, keywords, &mut send, delay).await;
+                    process(tool, source, keywords, &mut send, delay, jitter).await;
                     success = true;
                 }
             }
@@ -358,7 +527,7 @@ pub fn start_task(
                 let tool = hexun::Hexun::init(builder).await;
 
                 if let Some(tool) = try_unwrap(tool, &mut send).await {
-                    process(tool, keywords, &mut send, delay).await;
+                    process(tool, source, keywords, &mut send, delay, jitter).await;
                     success = true;
                 }
             }
@@ -366,7 +535,7 @@ pub fn start_task(
                 let tool = sohu::SoHu::init(builder).await;
 
                 if let Some(tool) = try_unwrap(tool, &mut send).await {
-                    process(tool, keywords, &mut send, delay).await;
+                    process(tool, source, keywords, &mut send, delay, jitter).await;
                     success = true;
                 }
             }
@@ -376,64 +545,132 @@ pub fn start_task(
     })
 }
 
-pub async fn process<T>(tool: T, keywords: Vec<String>, send: &mut Sender<Message>, delay: u64)
-where
+/// Write the exported content, the default file name is appended if the `path`
+/// is a directory, and the missing parent directories are created.
+pub async fn write_export(path: String, default: String, content: String) -> Message {
+    let mut file = PathBuf::from(&path);
+
+    if path.ends_with(['/', '\\']) || tokio::fs::metadata(&file).await.is_ok_and(|v| v.is_dir()) {
+        file.push(default);
+    }
+    if file.file_name().is_none() {
+        return Message::SetInfobar(format!("请指定文件名: {path}"));
+    }
+    if let Some(parent) = file.parent().filter(|v| !v.as_os_str().is_empty())
+        && let Err(e) = tokio::fs::create_dir_all(parent).await
+    {
+        return Message::SetInfobar(format!("创建目录错误: {e:?}"));
+    }
+    match tokio::fs::write(&file, content).await {
+        Ok(()) => Message::SetInfobar(format!("已导出到 {}", file.display())),
+        Err(e) => Message::SetInfobar(format!("写入文件错误: {e:?}")),
+    }
+}
+
+pub async fn process<T>(
+    tool: T,
+    source: Tool,
+    keywords: Vec<String>,
+    send: &mut Sender<Message>,
+    delay: u64,
+    jitter: u64,
+) where
     T: Search,
-    T::Input: Default,
+    T::Input: Clone + Default,
 {
-    let mut input = <T::Input>::default();
-
-    for keyword in keywords {
-        send.send(Message::SetInfobar(format!("搜索关键字 `{keyword}`...")))
+    if let Err(e) = tool.warm_up().await {
+        send.send(Message::SetInfobar(format!("预热失败: {e}")))
             .await
             .unwrap();
+    }
+
+    let mut reporter = GuiReporter {
+        send,
+        pending: VecDeque::default(),
+        delay,
+        jitter,
+    };
+
+    let report = Batch::default()
+        .with_source(Some(source))
+        .run_report(&tool, <T::Input>::default(), keywords, &mut reporter)
+        .await;
+
+    reporter.pending.push_back(Message::SetInfobar(format!(
+        "搜索完成: 成功 {}, 失败 {}, 用时 {:.1}s",
+        report.resolved.len(),
+        report.failed.len(),
+        report.elapsed.as_secs_f64()
+    )));
+    // wait for the room of the messages buffered while the channel is full
+    while let Some(message) = reporter.pending.pop_front() {
+        // the receiver is gone when the task is stopped
+        if reporter.send.send(message).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Forward the progress to the gui as messages.
+pub struct GuiReporter<'a> {
+    send: &'a mut Sender<Message>,
+    /// The messages not sent yet while the channel is full, in order
+    pending: VecDeque<Message>,
+    delay: u64,
+    jitter: u64,
+}
 
-        let stock = tool
-            .search({
-                input.reset();
-                input.set_keyword(keyword.clone());
-                &input
-            })
-            .await;
-
-        match stock {
-            Ok(stock) => {
-                let report =
-                    Message::SetInfobar(format!("搜索关键字 `{keyword}` ====> {}", stock.code));
-
-                send.send(report).await.unwrap();
-                send.send(Message::TaskReport(TaskReport::Stock(stock)))
-                    .await
-                    .unwrap();
+impl Reporter for GuiReporter<'_> {
+    fn report(&mut self, progress: Progress<'_>) -> ControlFlow<()> {
+        let messages = match progress {
+            Progress::Started { keyword, .. } => {
+                vec![Message::SetInfobar(format!("搜索关键字 `{keyword}`..."))]
             }
-            Err(e) => {
-                send.send(Message::TaskReport(TaskReport::Failed((
-                    keyword,
-                    e.to_string(),
-                ))))
-                .await
-                .unwrap();
+            Progress::Resolved { keyword, stock, .. } => vec![
+                Message::SetInfobar(format!("搜索关键字 `{keyword}` ====> {}", stock.code)),
+                Message::TaskReport(TaskReport::Stock(stock.clone())),
+            ],
+            Progress::Failed { keyword, error, .. } => vec![Message::TaskReport(
+                TaskReport::Failed((keyword.to_string(), error.to_string())),
+            )],
+            _ => vec![],
+        };
+
+        self.pending.extend(messages);
+        while let Some(message) = self.pending.pop_front() {
+            match self.send.try_send(message) {
+                Ok(()) => {}
+                // the receiver is gone when the task is stopped
+                Err(e) if e.is_disconnected() => return ControlFlow::Break(()),
+                Err(e) => {
+                    // keep the order, send it with the later messages
+                    self.pending.push_front(e.into_inner());
+                    break;
+                }
             }
         }
+        ControlFlow::Continue(())
+    }
+
+    fn delay(&mut self) -> Duration {
+        let jitter = rand::rng().random_range(0..=self.jitter);
 
-        tokio::time::sleep(Duration::from_millis(delay)).await;
+        Duration::from_millis(self.delay + jitter)
     }
 }
 
-pub fn progress_style(
-    progress: f32,
-) -> impl Fn(&Theme, iced::widget::text_input::Status) -> text_input::Style {
-    move |theme: &Theme, status| {
-        let mut style = text_input::default(theme, status);
+pub fn progress_style(progress: f32) -> impl Fn(&Theme) -> container::Style {
+    move |theme: &Theme| {
+        let mut style = container::bordered_box(theme);
         let palette = theme.palette();
 
-        style.background = Background::Gradient(Gradient::Linear({
+        style.background = Some(Background::Gradient(Gradient::Linear({
             iced::gradient::Linear::new(iced::Radians::PI * 0.5)
                 .add_stop(0., palette.danger)
                 .add_stop(progress, palette.danger)
                 .add_stop(progress + 0.0001, palette.background)
                 .add_stop(1.0, palette.background)
-        }));
+        })));
 
         style
     }
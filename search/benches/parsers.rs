@@ -0,0 +1,43 @@
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+
+const CNINFO: &str = include_str!("../tests/fixtures/cninfo.json");
+const SINA: &str = include_str!("../tests/fixtures/sina.txt");
+const CFI: &str = include_str!("../tests/fixtures/cfi.html");
+const HEXUN: &str = include_str!("../tests/fixtures/hexun.txt");
+const SOHU: &str = include_str!("../tests/fixtures/sohu.txt");
+
+/// A cfi response with many rows, the char scanning loop is linear in its size.
+fn cfi_large(rows: usize) -> String {
+    let row = "<tr><td>600519</td><td style=cursor:pointer;>贵州茅台</td></tr>";
+
+    format!(
+        "<table><tr><td>代码</td><td>名称</td></tr>{}</table>",
+        row.repeat(rows)
+    )
+}
+
+fn parsers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    let cfi_large = cfi_large(1000);
+
+    group.throughput(Throughput::Bytes(CNINFO.len() as u64));
+    group.bench_function("cninfo", |b| b.iter(|| search::cninfo::parse(CNINFO)));
+    group.throughput(Throughput::Bytes(SINA.len() as u64));
+    group.bench_function("sina", |b| b.iter(|| search::sina::parse(SINA)));
+    group.throughput(Throughput::Bytes(CFI.len() as u64));
+    group.bench_function("cfi", |b| b.iter(|| search::cfi::parse(CFI)));
+    group.throughput(Throughput::Bytes(cfi_large.len() as u64));
+    group.bench_function("cfi_large", |b| b.iter(|| search::cfi::parse(&cfi_large)));
+    // the first match without scanning the rest, compare with the eager `cfi_large`
+    group.bench_function("cfi_large_first", |b| {
+        b.iter(|| search::cfi::parse_iter(&cfi_large).next())
+    });
+    group.throughput(Throughput::Bytes(HEXUN.len() as u64));
+    group.bench_function("hexun", |b| b.iter(|| search::hexun::parse(HEXUN)));
+    group.throughput(Throughput::Bytes(SOHU.len() as u64));
+    group.bench_function("sohu", |b| b.iter(|| search::sohu::parse(SOHU)));
+    group.finish();
+}
+
+criterion_group!(benches, parsers);
+criterion_main!(benches);
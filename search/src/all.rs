@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use reqwest::Client;
+use tokio::sync::Semaphore;
+
+use crate::limiter::RateLimiter;
+use crate::probe::Probe;
+use crate::{QueryInput, Search, Stock, Tool, cfi, cninfo, hexun, sina, sohu};
+
+/// One of the sources, the outputs are converted to the stocks.
+#[derive(Debug, Clone)]
+pub enum Source {
+    CnInfo(cninfo::CnInfo),
+    Sina(sina::Sina),
+    Cfi(cfi::Cfi),
+    HeXun(hexun::Hexun),
+    SoHu(sohu::SoHu),
+}
+
+impl Source {
+    pub async fn init_with_probe(
+        tool: Tool,
+        client: Client,
+        probe: &Probe,
+    ) -> color_eyre::Result<Self> {
+        Ok(match tool {
+            Tool::CnInfo => Source::CnInfo(cninfo::CnInfo::init_with_probe(client, probe).await?),
+            Tool::Sina => Source::Sina(sina::Sina::init_with_probe(client, probe).await?),
+            Tool::Cfi => Source::Cfi(cfi::Cfi::init_with_probe(client, probe).await?),
+            Tool::HeXun => Source::HeXun(hexun::Hexun::init_with_probe(client, probe).await?),
+            Tool::SoHu => Source::SoHu(sohu::SoHu::init_with_probe(client, probe).await?),
+        })
+    }
+
+    pub fn tool(&self) -> Tool {
+        match self {
+            Source::CnInfo(_) => Tool::CnInfo,
+            Source::Sina(_) => Tool::Sina,
+            Source::Cfi(_) => Tool::Cfi,
+            Source::HeXun(_) => Tool::HeXun,
+            Source::SoHu(_) => Tool::SoHu,
+        }
+    }
+
+    pub fn with_dump_dir(self, dump_dir: Option<PathBuf>) -> Self {
+        match self {
+            Source::CnInfo(v) => Source::CnInfo(v.with_dump_dir(dump_dir)),
+            Source::Sina(v) => Source::Sina(v.with_dump_dir(dump_dir)),
+            Source::Cfi(v) => Source::Cfi(v.with_dump_dir(dump_dir)),
+            Source::HeXun(v) => Source::HeXun(v.with_dump_dir(dump_dir)),
+            Source::SoHu(v) => Source::SoHu(v.with_dump_dir(dump_dir)),
+        }
+    }
+
+    pub async fn warm_up(&self) -> color_eyre::Result<()> {
+        match self {
+            Source::CnInfo(v) => v.warm_up().await,
+            Source::Sina(v) => v.warm_up().await,
+            Source::Cfi(v) => v.warm_up().await,
+            Source::HeXun(v) => v.warm_up().await,
+            Source::SoHu(v) => v.warm_up().await,
+        }
+    }
+
+    /// Search the keyword and convert the outputs, [`Stock::source`] is set to
+    /// the tool. The `hk` only changes the cninfo search. Fail with the
+    /// conversion errors if none of the outputs is converted, see
+    /// [`crate::ConversionErrors`].
+    pub async fn search_stocks(&self, keyword: &str, hk: bool) -> color_eyre::Result<Vec<Stock>> {
+        let mut stocks = match self {
+            Source::CnInfo(v) => {
+                let input = cninfo::Input::new(keyword).with_hk(hk);
+
+                convert(v.search_all(&input).await?)?
+            }
+            Source::Sina(v) => convert(v.search_all(&sina::Input::new(keyword)).await?)?,
+            Source::Cfi(v) => convert(v.search_all(&cfi::Input::new(keyword)).await?)?,
+            Source::HeXun(v) => convert(v.search_all(&hexun::Input::new(keyword)).await?)?,
+            Source::SoHu(v) => convert(v.search_all(&sohu::Input::new(keyword)).await?)?,
+        };
+
+        for stock in &mut stocks {
+            stock.source = Some(self.tool());
+        }
+        Ok(stocks)
+    }
+}
+
+fn convert<T>(outputs: Vec<T>) -> color_eyre::Result<Vec<Stock>>
+where
+    T: TryInto<Stock>,
+    T::Error: std::fmt::Display,
+{
+    crate::convert_outputs(outputs, |v| v.try_into().map(Some))
+}
+
+/// Search the keyword with all the sources.
+///
+/// By default the sources are raced and the first non-empty results win, it
+/// is fast but the winner may differ between runs. In the [`All::stable`]
+/// mode the sources are queried one by one in the priority order, it is
+/// slower but the output is reproducible.
+#[derive(Debug, Clone, Default)]
+pub struct All {
+    /// The sources in the priority order
+    pub sources: Vec<Source>,
+
+    /// Query the sources in order instead of racing them
+    pub stable: bool,
+
+    /// Shared by the sources, each request of any source takes a token
+    pub limiter: Option<RateLimiter>,
+
+    /// The permits of the in-flight searches of each source, shared by the
+    /// clones, the sources not in it are not limited
+    pub permits: HashMap<Tool, Arc<Semaphore>>,
+}
+
+impl All {
+    /// Initialize the `tools` in the priority order, the sources failed to
+    /// initialize are skipped.
+    pub async fn init_with_probe(
+        client: Client,
+        probe: &Probe,
+        tools: &[Tool],
+    ) -> color_eyre::Result<Self> {
+        let mut sources = vec![];
+        let mut error = None;
+
+        for tool in tools {
+            match Source::init_with_probe(*tool, client.clone(), probe).await {
+                Ok(source) => sources.push(source),
+                Err(e) => error = Some(e),
+            }
+        }
+        if sources.is_empty() {
+            return Err(error.unwrap_or_else(|| color_eyre::eyre::eyre!("No search tool is given")));
+        }
+        Ok(Self {
+            sources,
+            ..Default::default()
+        })
+    }
+
+    pub fn with_stable(mut self, stable: bool) -> Self {
+        self.stable = stable;
+        self
+    }
+
+    pub fn with_limiter(mut self, limiter: Option<RateLimiter>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Dump the responses of all the sources to the directory.
+    pub fn with_dump_dir(mut self, dump_dir: Option<PathBuf>) -> Self {
+        self.sources = self
+            .sources
+            .into_iter()
+            .map(|v| v.with_dump_dir(dump_dir.clone()))
+            .collect();
+        self
+    }
+
+    /// Limit the in-flight searches of the tools, such as `hexun=2`, the zero
+    /// counts are taken as one.
+    pub fn with_tool_concurrency(mut self, concurrency: &HashMap<Tool, usize>) -> Self {
+        self.permits = concurrency
+            .iter()
+            .map(|(tool, count)| (*tool, Arc::new(Semaphore::new((*count).max(1)))))
+            .collect();
+        self
+    }
+
+    /// Search the source after it has a permit and a token.
+    async fn search_source(
+        &self,
+        source: &Source,
+        input: &Input,
+    ) -> color_eyre::Result<Vec<Stock>> {
+        let _permit = match self.permits.get(&source.tool()) {
+            Some(permits) => Some(permits.acquire().await?),
+            None => None,
+        };
+
+        self.throttle().await;
+        source.search_stocks(&input.key, input.hk).await
+    }
+
+    /// Wait for the [`All::limiter`] before a request.
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire().await;
+        }
+    }
+}
+
+/// Parse the concurrency of the tools, such as `hexun=2,sina=8`.
+pub fn parse_tool_concurrency(val: &str) -> color_eyre::Result<HashMap<Tool, usize>> {
+    let mut concurrency = HashMap::new();
+
+    for item in val.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+        let (tool, count) = item.split_once('=').ok_or_else(|| {
+            color_eyre::eyre::eyre!("Not a valid tool concurrency `{item}`, expect `tool=count`")
+        })?;
+        let count = count.trim().parse::<usize>().map_err(|e| {
+            color_eyre::eyre::eyre!("Not a valid concurrency `{count}` of {tool}: {e}")
+        })?;
+
+        concurrency.insert(tool.trim().parse::<Tool>()?, count);
+    }
+    Ok(concurrency)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Input {
+    pub key: String,
+
+    /// Search the HK securities of cninfo
+    pub hk: bool,
+}
+
+impl Input {
+    pub fn new(keyword: impl Into<String>) -> Self {
+        Self {
+            key: keyword.into(),
+            hk: false,
+        }
+    }
+
+    pub fn with_hk(mut self, hk: bool) -> Self {
+        self.hk = hk;
+        self
+    }
+}
+
+impl QueryInput for Input {
+    fn set_keyword(&mut self, keyword: String) {
+        self.key = keyword;
+    }
+
+    fn keyword(&self) -> &str {
+        &self.key
+    }
+}
+
+type Searching<'a> = Pin<Box<dyn Future<Output = color_eyre::Result<Vec<Stock>>> + 'a>>;
+
+impl Search for All {
+    type Input = Input;
+    type Output = Stock;
+
+    /// Warm up all the sources, fail only if all of them failed.
+    async fn warm_up(&self) -> color_eyre::Result<()> {
+        let mut error = None;
+        let mut warmed = false;
+
+        for source in &self.sources {
+            self.throttle().await;
+            match source.warm_up().await {
+                Ok(()) => warmed = true,
+                Err(e) => error = Some(e),
+            }
+        }
+        match error {
+            Some(e) if !warmed => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    async fn search_all(&self, input: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
+        if self.stable {
+            let mut error = None;
+
+            for source in &self.sources {
+                match self.search_source(source, input).await {
+                    Ok(stocks) if !stocks.is_empty() => return Ok(stocks),
+                    Ok(_) => {}
+                    Err(e) => error = Some(e),
+                }
+            }
+            return error.map_or(Ok(vec![]), Err);
+        }
+
+        if self.sources.is_empty() {
+            return Ok(vec![]);
+        }
+        let searching = self.sources.iter().map(|source| -> Searching<'_> {
+            Box::pin(async move {
+                let stocks = self.search_source(source, input).await?;
+
+                if stocks.is_empty() {
+                    return Err(color_eyre::eyre::eyre!(
+                        "{} found nothing",
+                        source.tool().name()
+                    ));
+                }
+                Ok(stocks)
+            })
+        });
+
+        Ok(futures::future::select_ok(searching).await?.0)
+    }
+
+    /// Fill the industry by cninfo if it is one of the sources.
+    async fn enrich(&self, stock: &mut Stock) -> color_eyre::Result<()> {
+        for source in &self.sources {
+            if let Source::CnInfo(v) = source {
+                self.throttle().await;
+                return v.enrich(stock).await;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,555 @@
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use tokio_util::sync::CancellationToken;
+
+use crate::limiter::RateLimiter;
+use crate::offline::Offline;
+use crate::synonyms::Synonyms;
+use crate::{Confidence, QueryInput, Search, SearchOptions, Stock, Tool};
+
+/// The progress events of [`Batch::run`].
+#[derive(Debug)]
+pub enum Progress<'a> {
+    /// The first event, before any keyword is searched
+    Begin {
+        total: usize,
+    },
+    Started {
+        index: usize,
+        keyword: &'a str,
+    },
+    Resolved {
+        index: usize,
+        keyword: &'a str,
+        stock: &'a Stock,
+        elapsed: Duration,
+    },
+    Failed {
+        index: usize,
+        keyword: &'a str,
+        error: &'a color_eyre::Report,
+        elapsed: Duration,
+        /// The source answered but no candidate is selected, such as the name
+        /// is unknown, the request itself did not fail
+        missing: bool,
+    },
+    /// The stock is resolved but [`Search::enrich`] failed
+    EnrichFailed {
+        index: usize,
+        keyword: &'a str,
+        error: &'a color_eyre::Report,
+    },
+    /// The last event, also sent when the batch is stopped
+    Done {
+        resolved: usize,
+        failed: usize,
+    },
+}
+
+/// Receive the progress of [`Batch::run`], any `FnMut(Progress) -> ControlFlow<()>`
+/// is a reporter. Return [`ControlFlow::Break`] to stop the batch.
+pub trait Reporter {
+    fn report(&mut self, progress: Progress<'_>) -> ControlFlow<()>;
+
+    /// Select the stock from the candidates of the keyword.
+    fn select(
+        &mut self,
+        _keyword: &str,
+        candidates: Vec<Stock>,
+        options: &SearchOptions,
+    ) -> color_eyre::Result<Stock> {
+        options.select_or_err(candidates)
+    }
+
+    /// The delay before searching the next keyword.
+    fn delay(&mut self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+impl<F> Reporter for F
+where
+    F: FnMut(Progress<'_>) -> ControlFlow<()>,
+{
+    fn report(&mut self, progress: Progress<'_>) -> ControlFlow<()> {
+        self(progress)
+    }
+}
+
+/// The error of a failed keyword, keep the message only so it can be
+/// serialized.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct SearchError {
+    pub message: String,
+}
+
+impl SearchError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl From<&color_eyre::Report> for SearchError {
+    fn from(value: &color_eyre::Report) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+impl std::fmt::Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// The summary of [`Batch::run_report`], both lists are in the order of the
+/// keywords. The keywords not finished at the cancellation are in neither.
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+pub struct BatchReport {
+    pub resolved: Vec<(String, Stock)>,
+    pub failed: Vec<(String, SearchError)>,
+    pub elapsed: Duration,
+}
+
+impl BatchReport {
+    pub fn to_json(&self) -> color_eyre::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// When to search a keyword again with the same source, each keyword is retried
+/// at most once, after the [`Reporter::delay`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// Retry when the source found nothing, the name may be genuinely unknown
+    pub retry_on_empty: bool,
+
+    /// Retry when the source failed, such as a transient network error
+    pub retry_on_error: bool,
+}
+
+impl RetryConfig {
+    pub fn with_retry_on_empty(mut self, retry_on_empty: bool) -> Self {
+        self.retry_on_empty = retry_on_empty;
+        self
+    }
+
+    pub fn with_retry_on_error(mut self, retry_on_error: bool) -> Self {
+        self.retry_on_error = retry_on_error;
+        self
+    }
+
+    /// Check the candidates should be searched again.
+    pub fn should_retry(&self, candidates: &color_eyre::Result<Vec<Stock>>) -> bool {
+        match candidates {
+            Ok(candidates) => self.retry_on_empty && candidates.is_empty(),
+            Err(_) => self.retry_on_error,
+        }
+    }
+}
+
+/// Search the keywords one by one with a source.
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    pub options: SearchOptions,
+
+    /// Convert the traditional chinese keywords to simplified before searching
+    pub simplify: bool,
+
+    /// Call [`Search::enrich`] on each resolved stock
+    pub enrich: bool,
+
+    /// Set to [`Stock::source`] of the resolved stocks, `None` keeps the one
+    /// set by the source, such as [`crate::all::All`]
+    pub source: Option<Tool>,
+
+    /// Keep the keyword in [`Stock::query`] of the resolved stocks
+    pub keep_query: bool,
+
+    /// Stop the batch and return the partial results when cancelled
+    pub cancel: Option<CancellationToken>,
+
+    /// Expand the abbreviations before searching, the reports and
+    /// [`Stock::query`] keep the original keyword
+    pub synonyms: Synonyms,
+
+    /// Search the keyword again on the empty results or the errors
+    pub retry: RetryConfig,
+
+    /// Resolve the keywords recognized by [`crate::normalize_stock_number`]
+    /// without searching, the names of them are empty
+    pub passthrough: bool,
+
+    /// Resolve the keywords found in the table without searching, the
+    /// keywords are looked up after the synonyms are expanded
+    pub offline: Offline,
+
+    /// Search at most the count of keywords at the same time, `0` and `1` are
+    /// sequential. The results keep the order of the keywords regardless of
+    /// which search completes first, the progress is reported in the order of
+    /// completion.
+    pub concurrency: usize,
+
+    /// Take a token before each search and enrichment, the concurrent searches
+    /// share it. Give it to [`crate::all::All`] instead to throttle each of the
+    /// aggregated sources
+    pub limiter: Option<RateLimiter>,
+}
+
+impl Batch {
+    pub fn with_options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_simplify(mut self, simplify: bool) -> Self {
+        self.simplify = simplify;
+        self
+    }
+
+    pub fn with_enrich(mut self, enrich: bool) -> Self {
+        self.enrich = enrich;
+        self
+    }
+
+    pub fn with_source(mut self, source: Option<Tool>) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn with_keep_query(mut self, keep_query: bool) -> Self {
+        self.keep_query = keep_query;
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: Option<CancellationToken>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn with_synonyms(mut self, synonyms: Synonyms) -> Self {
+        self.synonyms = synonyms;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_passthrough(mut self, passthrough: bool) -> Self {
+        self.passthrough = passthrough;
+        self
+    }
+
+    pub fn with_offline(mut self, offline: Offline) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn with_limiter(mut self, limiter: Option<RateLimiter>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Wait for the [`Batch::limiter`] before a request, return `None` if cancelled.
+    async fn throttle(&self) -> Option<()> {
+        match &self.limiter {
+            Some(limiter) => self.cancellable(limiter.acquire()).await,
+            None => Some(()),
+        }
+    }
+
+    /// The stock of the keyword if it is a code accepted by the options.
+    fn passthrough_code(&self, keyword: &str) -> Option<Stock> {
+        if !self.passthrough {
+            return None;
+        }
+        let keyword = crate::half_width(keyword.trim());
+
+        crate::normalize_stock_number(&keyword)?;
+        Stock::try_from(&*keyword)
+            .ok()
+            .and_then(|v| self.options.accept(v))
+    }
+
+    /// The stock of the keyword in the [`Batch::offline`] table if it is
+    /// accepted by the options.
+    fn offline_stock(&self, keyword: &str) -> Option<Stock> {
+        self.offline
+            .get(keyword)
+            .cloned()
+            .and_then(|v| self.options.accept(v))
+    }
+
+    /// Race the future with the cancellation, return `None` if cancelled.
+    async fn cancellable<F: Future>(&self, future: F) -> Option<F::Output> {
+        match &self.cancel {
+            Some(cancel) => tokio::select! {
+                _ = cancel.cancelled() => None,
+                v = future => Some(v),
+            },
+            None => Some(future.await),
+        }
+    }
+
+    /// Search the keywords, the results are in the order of the keywords and
+    /// the failed keywords are kept with `None`. The keywords not finished at
+    /// the cancellation are not in the results.
+    pub async fn run<T, R>(
+        &self,
+        tool: &T,
+        input: T::Input,
+        keywords: Vec<String>,
+        reporter: &mut R,
+    ) -> Vec<(String, Option<Stock>)>
+    where
+        T: Search,
+        T::Input: Clone,
+        R: Reporter,
+    {
+        self.run_with(tool, input, keywords, reporter, false)
+            .await
+            .into_iter()
+            .map(|(keyword, stocks)| (keyword, stocks.ok().and_then(|v| v.into_iter().next())))
+            .collect()
+    }
+
+    /// Same as [`Batch::run`], but return the resolved and the failed keywords
+    /// with the errors in a [`BatchReport`].
+    pub async fn run_report<T, R>(
+        &self,
+        tool: &T,
+        input: T::Input,
+        keywords: Vec<String>,
+        reporter: &mut R,
+    ) -> BatchReport
+    where
+        T: Search,
+        T::Input: Clone,
+        R: Reporter,
+    {
+        let start = Instant::now();
+        let mut report = BatchReport::default();
+
+        for (keyword, stocks) in self.run_with(tool, input, keywords, reporter, false).await {
+            match stocks.map(|v| v.into_iter().next()) {
+                Ok(Some(stock)) => report.resolved.push((keyword, stock)),
+                Ok(None) => report
+                    .failed
+                    .push((keyword, SearchError::new("found nothing"))),
+                Err(e) => report.failed.push((keyword, e)),
+            }
+        }
+        report.elapsed = start.elapsed();
+        report
+    }
+
+    /// Same as [`Batch::run`], but keep all the candidates of each keyword
+    /// instead of selecting one, the failed keywords are kept with an empty
+    /// list. A [`Progress::Resolved`] is reported for each candidate.
+    pub async fn run_all<T, R>(
+        &self,
+        tool: &T,
+        input: T::Input,
+        keywords: Vec<String>,
+        reporter: &mut R,
+    ) -> Vec<(String, Vec<Stock>)>
+    where
+        T: Search,
+        T::Input: Clone,
+        R: Reporter,
+    {
+        self.run_with(tool, input, keywords, reporter, true)
+            .await
+            .into_iter()
+            .map(|(keyword, stocks)| (keyword, stocks.unwrap_or_default()))
+            .collect()
+    }
+
+    /// Search the keyword with the retry, the `delay` is waited before the
+    /// search if `wait`, and before the retry. Return `None` if cancelled.
+    async fn search_keyword<T: Search>(
+        &self,
+        tool: &T,
+        input: &T::Input,
+        code: Option<Stock>,
+        delay: Duration,
+        wait: bool,
+    ) -> Option<(color_eyre::Result<Vec<Stock>>, Duration)> {
+        if wait {
+            self.cancellable(tokio::time::sleep(delay)).await?;
+        }
+        let start = Instant::now();
+
+        if let Some(stock) = code {
+            return Some((Ok(vec![stock]), start.elapsed()));
+        }
+        let mut retried = false;
+
+        loop {
+            self.throttle().await?;
+
+            let candidates = self
+                .cancellable(tool.search_candidates(input, &self.options))
+                .await?;
+
+            if retried || !self.retry.should_retry(&candidates) {
+                return Some((candidates, start.elapsed()));
+            }
+            retried = true;
+            self.cancellable(tokio::time::sleep(delay)).await?;
+        }
+    }
+
+    async fn run_with<T, R>(
+        &self,
+        tool: &T,
+        input: T::Input,
+        keywords: Vec<String>,
+        reporter: &mut R,
+        all: bool,
+    ) -> Vec<(String, Result<Vec<Stock>, SearchError>)>
+    where
+        T: Search,
+        T::Input: Clone,
+        R: Reporter,
+    {
+        let total = keywords.len();
+        let mut results = Vec::with_capacity(total);
+        let mut resolved = 0;
+        let mut keywords = keywords.into_iter().enumerate();
+        let mut pending = FuturesUnordered::new();
+        let mut flow = reporter.report(Progress::Begin { total });
+
+        while flow.is_continue() {
+            while pending.len() < self.concurrency.max(1)
+                && let Some((index, keyword)) = keywords.next()
+            {
+                flow = reporter.report(Progress::Started {
+                    index,
+                    keyword: &keyword,
+                });
+                if flow.is_break() {
+                    break;
+                }
+
+                let mut input = input.clone();
+                let simplified = if self.simplify {
+                    crate::chinese::simplify(&keyword)
+                } else {
+                    keyword.clone()
+                };
+
+                input.reset();
+                input.set_keyword(self.synonyms.expand(&simplified).to_string());
+
+                let searched = input.keyword().to_string();
+                let code = self
+                    .passthrough_code(&keyword)
+                    .or_else(|| self.offline_stock(&searched));
+                let passed = code.is_some();
+                // the codes passed through and the offline stocks send no request
+                let wait = index > 0 && !passed;
+                let delay = reporter.delay();
+
+                pending.push(async move {
+                    let candidates = self.search_keyword(tool, &input, code, delay, wait).await;
+
+                    (index, keyword, searched, passed, candidates)
+                });
+            }
+            if flow.is_break() {
+                break;
+            }
+            let Some((index, keyword, searched, passed, Some((candidates, elapsed)))) =
+                pending.next().await
+            else {
+                // all the keywords are searched or cancelled
+                break;
+            };
+            let missing = candidates.is_ok();
+            let stocks = match candidates {
+                Ok(candidates) if all && !candidates.is_empty() => Ok(candidates),
+                Ok(candidates) if all => self.options.select_or_err(candidates).map(|v| vec![v]),
+                Ok(candidates) => reporter
+                    .select(&keyword, candidates, &self.options)
+                    .map(|v| vec![v]),
+                Err(e) => Err(e),
+            };
+
+            flow = match stocks {
+                Ok(mut stocks) => {
+                    let mut flow = ControlFlow::Continue(());
+
+                    for stock in stocks.iter_mut() {
+                        // graded by the keyword searched, the synonyms are expanded
+                        stock.confidence = Some(Confidence::of(&searched, stock));
+                        // keep the source set by the aggregated sources, the
+                        // codes passed through and the offline stocks are not
+                        // from any source
+                        if !passed {
+                            stock.source = self.source.or(stock.source);
+                        }
+                        if self.keep_query {
+                            stock.query = Some(keyword.clone());
+                        }
+                        if self.enrich
+                            && self.throttle().await.is_some()
+                            && let Err(error) = tool.enrich(stock).await
+                        {
+                            let _ = reporter.report(Progress::EnrichFailed {
+                                index,
+                                keyword: &keyword,
+                                error: &error,
+                            });
+                        }
+                        if flow.is_continue() {
+                            flow = reporter.report(Progress::Resolved {
+                                index,
+                                keyword: &keyword,
+                                stock,
+                                elapsed,
+                            });
+                        }
+                    }
+                    resolved += 1;
+                    results.push((index, keyword, Ok(stocks)));
+                    flow
+                }
+                Err(error) => {
+                    let flow = reporter.report(Progress::Failed {
+                        index,
+                        keyword: &keyword,
+                        error: &error,
+                        elapsed,
+                        missing,
+                    });
+
+                    results.push((index, keyword, Err(SearchError::from(&error))));
+                    flow
+                }
+            };
+        }
+
+        let _ = reporter.report(Progress::Done {
+            resolved,
+            failed: results.len() - resolved,
+        });
+        // the concurrent searches complete in any order
+        results.sort_by_key(|(index, ..)| *index);
+        results
+            .into_iter()
+            .map(|(_, keyword, stocks)| (keyword, stocks))
+            .collect()
+    }
+}
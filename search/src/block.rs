@@ -0,0 +1,171 @@
+use std::path::Path;
+
+use crate::{Exchange, Stock, Valid};
+
+/// Read the block file, such as the `.ebk` of 通达信 or the `.blk` exported by
+/// 同花顺, see [`parse`].
+pub fn read(path: &Path) -> color_eyre::Result<Vec<Stock>> {
+    parse(&read_text(path)?)
+}
+
+fn read_text(path: &Path) -> color_eyre::Result<String> {
+    let bytes = std::fs::read(path)?;
+
+    Ok(match String::from_utf8(bytes) {
+        Ok(text) => text,
+        // the block files written by the windows clients are GBK
+        Err(e) => encoding_rs::GBK.decode(e.as_bytes()).0.into_owned(),
+    })
+}
+
+/// Read the block files and merge the codes, the codes with the same
+/// normalized code are kept once in the first seen order, see
+/// [`crate::dedup_stocks`].
+pub fn merge<P: AsRef<Path>>(paths: &[P]) -> color_eyre::Result<Vec<Stock>> {
+    let mut stocks = vec![];
+
+    for path in paths {
+        let path = path.as_ref();
+
+        stocks
+            .extend(read(path).map_err(|e| color_eyre::eyre::eyre!("{e} in {}", path.display()))?);
+    }
+    Ok(crate::dedup_stocks(stocks))
+}
+
+/// Parse the codes of the block file, one per line, the empty lines and the
+/// lines start with `#` are skipped. The block file has no names, the names
+/// of the stocks are empty.
+///
+/// The 6 digits HK code of 通达信, such as `500700`, is only read from the
+/// files of 通达信, see [`is_tdx`].
+pub fn parse(text: &str) -> color_eyre::Result<Vec<Stock>> {
+    let tdx = is_tdx(text);
+    let mut stocks = vec![];
+
+    for (index, line) in text.lines().map(str::trim).enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let stock = parse_code_in(line, tdx)
+            .map_err(|e| color_eyre::eyre::eyre!("{e} at line {}", index + 1))?;
+
+        stocks.push(stock);
+    }
+    Ok(stocks)
+}
+
+/// A line of the block file failed the [`check`].
+#[derive(Debug)]
+pub struct InvalidLine {
+    /// The line number, starts from 1
+    pub line: usize,
+
+    pub content: String,
+
+    pub error: color_eyre::Report,
+}
+
+/// Read the block file and check each line, see [`check`].
+pub fn check_file(path: &Path) -> color_eyre::Result<Vec<InvalidLine>> {
+    Ok(check(&read_text(path)?))
+}
+
+/// Check each line of the block file with [`parse_code`] and
+/// [`Stock::validate`], return all the invalid lines instead of the first
+/// one. The lines skipped by [`parse`] are not checked.
+pub fn check(text: &str) -> Vec<InvalidLine> {
+    let tdx = is_tdx(text);
+    let mut invalid = vec![];
+
+    for (index, line) in text.lines().map(str::trim).enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Err(error) = parse_code_in(line, tdx).and_then(|v| v.validate()) {
+            invalid.push(InvalidLine {
+                line: index + 1,
+                content: line.to_string(),
+                error,
+            });
+        }
+    }
+    invalid
+}
+
+/// Check the block file is written by 通达信, it starts with the empty line of
+/// [`EBK_HEADER`](crate::export::EBK_HEADER) or has the mainland codes with
+/// the market digit, such as `1600519`.
+pub fn is_tdx(text: &str) -> bool {
+    text.lines().next().is_some_and(|v| v.trim().is_empty())
+        || text.lines().map(str::trim).any(|v| {
+            v.len() == 7
+                && v.bytes().all(|v| v.is_ascii_digit())
+                && matches!(&v[0..1], "0" | "1" | "8")
+        })
+}
+
+/// Parse a code in any of the [`NormalizeStyle`](crate::NormalizeStyle), such
+/// as `1600519`, `sh600519`, `600519.SH` or `600519`. The plain code is
+/// guessed by [`Exchange::guess_from_stock`], so the 6 digits HK code of
+/// 通达信, such as `500700`, is not accepted, see [`parse`].
+pub fn parse_code(val: &str) -> color_eyre::Result<Stock> {
+    parse_code_in(val, false)
+}
+
+/// Same as [`parse_code`], but read the 6 digits code starts with `5` as the
+/// HK code of 通达信 if `tdx` is true.
+fn parse_code_in(val: &str, tdx: bool) -> color_eyre::Result<Stock> {
+    let val = val.trim();
+    let (exchange, code) =
+        split_code(val, tdx).ok_or_else(|| color_eyre::eyre::eyre!("Not a valid code `{val}`"))?;
+    let code = match exchange {
+        Exchange::HongKong => crate::pad_hk_code(code).unwrap_or_else(|| code.to_string()),
+        Exchange::UnitedStates => code.to_ascii_uppercase(),
+        _ => code.to_string(),
+    };
+
+    if exchange.valid(&code).is_none() {
+        return Err(color_eyre::eyre::eyre!(
+            "Not a valid code `{val}` of {exchange:?}"
+        ));
+    }
+    Ok(Stock::new(String::default(), code, exchange))
+}
+
+fn split_code(val: &str, tdx: bool) -> Option<(Exchange, &str)> {
+    let lower = val.to_ascii_lowercase();
+
+    for exchange in Exchange::ALL {
+        let short = exchange.short_name();
+
+        if lower.starts_with(short) {
+            return Some((exchange, &val[short.len()..]));
+        }
+        if let Some(code) = lower.strip_suffix(&format!(".{short}")) {
+            return Some((exchange, &val[..code.len()]));
+        }
+    }
+    // the shanghai suffix of yahoo
+    if let Some(code) = lower.strip_suffix(".ss") {
+        return Some((Exchange::ShangHai, &val[..code.len()]));
+    }
+    if let Some(ticker) = val.strip_prefix("74")
+        && ticker.starts_with(|v: char| v.is_ascii_alphabetic())
+    {
+        return Some((Exchange::UnitedStates, ticker));
+    }
+    if !val.bytes().all(|v| v.is_ascii_digit()) {
+        return None;
+    }
+    // the market digit of 通达信, the plain codes of mainland have 6 digits,
+    // such as the fund `510300` of shanghai, so the HK code is only read in
+    // the files of 通达信
+    match (val.len(), val.get(0..1)) {
+        (7, Some("1")) => Some((Exchange::ShangHai, &val[1..])),
+        (7, Some("0")) => Some((Exchange::ShenZhen, &val[1..])),
+        (7, Some("8")) => Some((Exchange::BeiJing, &val[1..])),
+        (6, Some("5")) if tdx => Some((Exchange::HongKong, &val[1..])),
+        _ => Exchange::guess_from_stock(val).ok().map(|v| (v, val)),
+    }
+}
@@ -1,23 +1,82 @@
-use color_eyre::eyre::eyre;
+use std::path::PathBuf;
+
 use reqwest::Client;
 
 use crate::{Exchange, Stock};
 
-#[derive(Debug)]
+pub const HOMEPAGE: &str = "https://stock.cfi.cn";
+
+/// The scheme and host of the api.
+pub const BASE_URL: &str = "https://quote.cfi.cn";
+
+/// The source is `Send + Sync`, cloning it only clones the reference counted
+/// client, the clones share the connection pool and the cookies.
+#[derive(Debug, Clone)]
 pub struct Cfi {
     pub reqwest: Client,
+
+    /// The scheme and host of the api, default is [`BASE_URL`]
+    pub base_url: String,
+
+    /// Write the response bodies to the directory, see [`crate::dump_response`]
+    pub dump_dir: Option<PathBuf>,
 }
 
 impl Cfi {
     pub async fn init(builder: reqwest::ClientBuilder) -> color_eyre::Result<Self> {
-        let reqwest = builder.build()?;
-        let res = reqwest.get("https://stock.cfi.cn").send().await?;
+        Self::init_with_client(crate::decompression(builder).build()?).await
+    }
 
-        if !res.status().is_success() {
-            return Err(eyre!("Can not access cfi website: {}", res.status()));
-        }
+    /// Initialize with a shared client, its connection pool and cookie store are reused.
+    pub async fn init_with_client(reqwest: Client) -> color_eyre::Result<Self> {
+        Self::init_with_probe(reqwest, &crate::probe::Probe::default()).await
+    }
+
+    /// Initialize with a shared client, check the reachability with the `probe`.
+    pub async fn init_with_probe(
+        reqwest: Client,
+        probe: &crate::probe::Probe,
+    ) -> color_eyre::Result<Self> {
+        let tool = Self {
+            reqwest,
+            base_url: BASE_URL.to_string(),
+            dump_dir: None,
+        };
+
+        probe.check(&tool, &tool.reqwest, HOMEPAGE, "cfi").await?;
+
+        Ok(tool)
+    }
 
-        Ok(Self { reqwest })
+    /// Send the requests to the `base_url` instead, such as a mirror or a mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_dump_dir(mut self, dump_dir: Option<PathBuf>) -> Self {
+        self.dump_dir = dump_dir;
+        self
+    }
+
+    /// Send the query, return the html table.
+    async fn fetch(&self, info: &Input) -> color_eyre::Result<String> {
+        let url = format!("{}/backgettext.aspx", self.base_url);
+        let res = self
+            .reqwest
+            .get(url)
+            .query(&[
+                ("keyword", &info.key),
+                ("his", &info.his),
+                ("longtime", &info.longtime),
+            ])
+            .send()
+            .await?;
+
+        let text = crate::response_text(res, "cfi").await?;
+
+        crate::dump_response(self.dump_dir.as_deref(), "cfi", &info.key, &text)?;
+        Ok(text)
     }
 }
 
@@ -32,7 +91,8 @@ pub struct Input {
 
 impl Default for Input {
     fn default() -> Self {
-        let local = chrono::Local::now();
+        // the time of the site, the time zone of the system may be broken
+        let local = Exchange::ShangHai.now();
         let longtime = format!("{}", local.format("%Y%m%d%H%M%S%3f"));
 
         Self {
@@ -43,10 +103,36 @@ impl Default for Input {
     }
 }
 
+impl Input {
+    pub fn new(keyword: impl Into<String>) -> Self {
+        Self::default().with_keyword(keyword)
+    }
+
+    pub fn with_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.key = keyword.into();
+        self
+    }
+
+    pub fn with_his(mut self, his: impl Into<String>) -> Self {
+        self.his = his.into();
+        self
+    }
+
+    /// Set the timestamp parameter, defaults to the local time
+    pub fn with_longtime(mut self, longtime: impl Into<String>) -> Self {
+        self.longtime = longtime.into();
+        self
+    }
+}
+
 impl crate::QueryInput for Input {
     fn set_keyword(&mut self, keyword: String) {
         self.key = keyword;
     }
+
+    fn keyword(&self) -> &str {
+        &self.key
+    }
 }
 
 #[derive(Debug)]
@@ -61,11 +147,7 @@ impl TryFrom<Output> for Stock {
     fn try_from(value: Output) -> Result<Self, Self::Error> {
         let exchange = Exchange::guess_from_stock(&value.code);
 
-        Ok(Stock {
-            name: value.name,
-            code: value.code,
-            exchange: exchange?,
-        })
+        Ok(Stock::new(value.name, value.code, exchange?))
     }
 }
 
@@ -73,53 +155,92 @@ impl crate::Search for Cfi {
     type Input = Input;
     type Output = Output;
 
+    async fn healthcheck(&self) -> color_eyre::Result<()> {
+        crate::probe::check(&self.reqwest, HOMEPAGE, "cfi").await
+    }
+
+    async fn warm_up(&self) -> color_eyre::Result<()> {
+        crate::probe::warm_up(&self.reqwest, HOMEPAGE, "cfi").await
+    }
+
     async fn search_all(&self, info: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
-        use neure::prelude::*;
+        parse(&self.fetch(info).await?)
+    }
 
-        let url = "https://quote.cfi.cn/backgettext.aspx";
-        let res = self
-            .reqwest
-            .get(url)
-            .query(&[
-                ("keyword", &info.key),
-                ("his", &info.his),
-                ("longtime", &info.longtime),
-            ])
-            .send()
-            .await?;
+    /// Stop scanning after the limit, the rest of a large response is not parsed.
+    async fn search_candidates(
+        &self,
+        input: &Self::Input,
+        options: &crate::SearchOptions,
+    ) -> color_eyre::Result<Vec<Stock>> {
+        options.check_keyword(crate::QueryInput::keyword(input))?;
+
+        let text = self.fetch(input).await?;
+        let limit = options.limit.unwrap_or(usize::MAX);
+        let outputs = parse_iter(&text)
+            .take(limit)
+            .collect::<color_eyre::Result<Vec<_>>>()?;
+        let candidates = crate::accept_outputs(outputs, options)?;
+
+        Ok(options.rank(crate::QueryInput::keyword(input), candidates))
+    }
+}
+
+/// Parse the html table returned by cfi.
+///
+/// A name is paired with the nearest code before it, a name without a
+/// preceding code is skipped.
+pub fn parse(text: &str) -> color_eyre::Result<Vec<Output>> {
+    parse_iter(text).collect()
+}
+
+/// Same as [`parse`], but yield each output once its row is scanned, the
+/// caller can stop without scanning the rest of a large response.
+pub fn parse_iter(text: &str) -> impl Iterator<Item = color_eyre::Result<Output>> + '_ {
+    use neure::prelude::*;
 
-        let text = res.text().await?;
+    let stock_code = neu::digit(10).repeat_times::<6>().quote(">", "</td>");
+    let stock_name = neu::ascii().not().repeat_one_more().quote(";>", "</td>");
+    let mut ctx = CharsCtx::new(text);
+    let mut curr_code: Option<&str> = None;
+    let mut failed = false;
 
-        let stock_code = neu::digit(10).repeat_times::<6>().quote(">", "</td>");
-        let stock_name = neu::ascii().not().repeat_one_more().quote(";>", "</td>");
-        let mut ctx = CharsCtx::new(&text);
-        let mut curr_code: Option<&str> = None;
-        let mut curr_name: Option<&str> = None;
-        let mut outputs = vec![];
+    std::iter::from_fn(move || {
+        while !failed && ctx.offset() < ctx.len() {
+            let offset = ctx.offset();
+            let mut output = None;
 
-        while !ctx.is_empty() && ctx.offset() < ctx.len() {
             if let Ok(code) = ctx.ctor(&stock_code) {
-                if curr_code.is_none() || curr_name.is_none() {
-                    curr_code = Some(code);
-                    curr_name = None;
-                } else if curr_code.is_some()
-                    && curr_name.is_some()
-                    && let (Some(code), Some(name)) = (curr_code.take(), curr_name.take())
-                {
-                    outputs.push(Output {
+                curr_code = Some(code);
+            } else if let Ok(name) = ctx.ctor(&stock_name) {
+                if let Some(code) = curr_code.take() {
+                    output = Some(Output {
                         code: code.to_string(),
                         name: name.to_string(),
                     });
                 }
-            } else if let Ok(name) = ctx.ctor(&stock_name) {
-                if curr_code.is_some() {
-                    curr_name = Some(name);
-                }
             } else {
-                ctx.inc(1);
+                // skip a whole character
+                let len = text
+                    .get(offset..)
+                    .and_then(|v| v.chars().next())
+                    .map_or(1, char::len_utf8);
+
+                ctx.inc(len);
+            }
+            if ctx.offset() <= offset {
+                failed = true;
+                return Some(Err(crate::parse_error(
+                    "cfi",
+                    text,
+                    offset,
+                    "the parser made no progress",
+                )));
+            }
+            if output.is_some() {
+                return output.map(Ok);
             }
         }
-
-        Ok(outputs)
-    }
+        None
+    })
 }
@@ -0,0 +1,69 @@
+//! A small bundled table converting the traditional chinese characters
+//! commonly seen in stock names.
+
+/// Characters converted in both directions.
+const TRADITIONAL: &str = "國華銀業電氣車東發開門長與興為實際產廣場證億萬網絡訊資財貿鐵\
+    鋼礦藥醫療農漁紡織機設備運輸龍鳳雲達偉創環體學傳視聽樂戲飛鳥\
+    馬魚雞麥糧飲務險壽豐聯總區縣灣島號線紅綠藍黃錢幣貨濟經營團統\
+    軟檢測權歐亞倫滬漢蘇蘭廈臨鄭陽寧遼濱齊魯閩贛粵瓊貴陝隴築裝飾\
+    潔淨燈熱動勢葉書報紙訂單價額稅會計師圍園莊鎮鄉觀點職員賓館錦\
+    繡綿鮮醬釀湯條餅鹽煙腦圖畫儀鐘鏈鋁銅鋅鎳鈦鋰鈷輪軸齒節衛導彈\
+    艦試驗質標圓寶齡頭順風談話語誠譽讓議論記許請認識譯詩調飯麗豔\
+    嬌優勝進遠選連這過還邊遞適鄰陳陸隊階隨隱雙難雜離靈韓頁項預領\
+    題顏願顯類養驅騰驚鬧齋龜層屬嶺廠廳張從戶擁擇據擴攝數斷時晉曉\
+    暢極構樓樣樹橋檔歷歸殼溝滿漲滯潤濕災無煉爭爾牆狀獨獲現畢異當\
+    盡監盤眾礎確禮種稱穩窮競筆簡紀約級納紗組結給絕維綜緊練縮績繼\
+    續羅義習聖聞聲肅腳膠臉舊艙藝蘋蠶術補見規親覺觸訓評詞詳說課諸\
+    講護讀變負貢敗販費賀賞賣購贈贊趕趙躍軍軒較載輕輝轉辦遲郵針鈴\
+    銷鋒錄鍵鏡鑄閃閉間閱關陰霧靜響頂頓頻顧飄駐鳴鵬鶴麼";
+
+const SIMPLIFIED: &str = "国华银业电气车东发开门长与兴为实际产广场证亿万网络讯资财贸铁\
+    钢矿药医疗农渔纺织机设备运输龙凤云达伟创环体学传视听乐戏飞鸟\
+    马鱼鸡麦粮饮务险寿丰联总区县湾岛号线红绿蓝黄钱币货济经营团统\
+    软检测权欧亚伦沪汉苏兰厦临郑阳宁辽滨齐鲁闽赣粤琼贵陕陇筑装饰\
+    洁净灯热动势叶书报纸订单价额税会计师围园庄镇乡观点职员宾馆锦\
+    绣绵鲜酱酿汤条饼盐烟脑图画仪钟链铝铜锌镍钛锂钴轮轴齿节卫导弹\
+    舰试验质标圆宝龄头顺风谈话语诚誉让议论记许请认识译诗调饭丽艳\
+    娇优胜进远选连这过还边递适邻陈陆队阶随隐双难杂离灵韩页项预领\
+    题颜愿显类养驱腾惊闹斋龟层属岭厂厅张从户拥择据扩摄数断时晋晓\
+    畅极构楼样树桥档历归壳沟满涨滞润湿灾无炼争尔墙状独获现毕异当\
+    尽监盘众础确礼种称稳穷竞笔简纪约级纳纱组结给绝维综紧练缩绩继\
+    续罗义习圣闻声肃脚胶脸旧舱艺苹蚕术补见规亲觉触训评词详说课诸\
+    讲护读变负贡败贩费贺赏卖购赠赞赶赵跃军轩较载轻辉转办迟邮针铃\
+    销锋录键镜铸闪闭间阅关阴雾静响顶顿频顾飘驻鸣鹏鹤么";
+
+/// Characters only converted to simplified, the simplified one is also used
+/// in traditional text.
+const TRADITIONAL_ONLY: &str = "係製複復麵鬆闆餘臺鬥誌準錶迴徵髮範遊";
+
+const SIMPLIFIED_ONLY: &str = "系制复复面松板余台斗志准表回征发范游";
+
+fn lookup(ch: char, tables: &[(&str, &str)]) -> Option<char> {
+    tables.iter().find_map(|(from, to)| {
+        from.chars()
+            .position(|v| v == ch)
+            .and_then(|index| to.chars().nth(index))
+    })
+}
+
+fn convert(val: &str, tables: &[(&str, &str)]) -> String {
+    val.chars()
+        .map(|ch| lookup(ch, tables).unwrap_or(ch))
+        .collect()
+}
+
+/// Convert the traditional characters in `val` to simplified.
+pub fn simplify(val: &str) -> String {
+    convert(
+        val,
+        &[
+            (TRADITIONAL, SIMPLIFIED),
+            (TRADITIONAL_ONLY, SIMPLIFIED_ONLY),
+        ],
+    )
+}
+
+/// Convert the simplified characters in `val` to traditional.
+pub fn traditionalize(val: &str) -> String {
+    convert(val, &[(SIMPLIFIED, TRADITIONAL)])
+}
@@ -1,23 +1,81 @@
-use color_eyre::eyre::eyre;
+use std::path::PathBuf;
+
 use reqwest::Client;
 
-use crate::{Exchange, Stock};
+use crate::{Exchange, SearchOptions, Stock};
+
+pub const HOMEPAGE: &str = "https://www.cninfo.com.cn";
+
+/// The scheme and host of the api.
+pub const BASE_URL: &str = "https://www.cninfo.com.cn";
 
-#[derive(Debug)]
+/// The source is `Send + Sync`, cloning it only clones the reference counted
+/// client, the clones share the connection pool and the cookies.
+#[derive(Debug, Clone)]
 pub struct CnInfo {
     pub reqwest: Client,
+
+    /// The scheme and host of the api, default is [`BASE_URL`]
+    pub base_url: String,
+
+    /// Write the response bodies to the directory, see [`crate::dump_response`]
+    pub dump_dir: Option<PathBuf>,
 }
 
 impl CnInfo {
     pub async fn init(builder: reqwest::ClientBuilder) -> color_eyre::Result<Self> {
-        let reqwest = builder.build()?;
-        let res = reqwest.get("https://www.cninfo.com.cn").send().await?;
+        Self::init_with_client(crate::decompression(builder).build()?).await
+    }
 
-        if !res.status().is_success() {
-            return Err(eyre!("Can not access cninfo website: {}", res.status()));
-        }
+    /// Initialize with a shared client, its connection pool and cookie store are reused.
+    pub async fn init_with_client(reqwest: Client) -> color_eyre::Result<Self> {
+        Self::init_with_probe(reqwest, &crate::probe::Probe::default()).await
+    }
+
+    /// Initialize with a shared client, check the reachability with the `probe`.
+    pub async fn init_with_probe(
+        reqwest: Client,
+        probe: &crate::probe::Probe,
+    ) -> color_eyre::Result<Self> {
+        let tool = Self {
+            reqwest,
+            base_url: BASE_URL.to_string(),
+            dump_dir: None,
+        };
+
+        probe
+            .check(&tool, &tool.reqwest, HOMEPAGE, "cninfo")
+            .await?;
+
+        Ok(tool)
+    }
+
+    /// Send the requests to the `base_url` instead, such as a mirror or a mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_dump_dir(mut self, dump_dir: Option<PathBuf>) -> Self {
+        self.dump_dir = dump_dir;
+        self
+    }
+
+    /// Same as [`crate::Search::search_candidates`], but keep the record of
+    /// each candidate, such as the [`Output::org_id`] of the disclosure api.
+    pub async fn search_records(
+        &self,
+        input: &Input,
+        options: &SearchOptions,
+    ) -> color_eyre::Result<Vec<(Stock, Output)>> {
+        options.check_keyword(&input.key)?;
+
+        let outputs = options.truncate(crate::Search::search_all(self, input).await?);
+        let records = crate::convert_outputs(outputs, |output| {
+            Stock::try_from(output.clone()).map(|v| options.accept(v).map(|v| (v, output)))
+        })?;
 
-        Ok(Self { reqwest })
+        Ok(options.rank_by(&input.key, records, |(stock, _)| stock))
     }
 }
 
@@ -26,6 +84,9 @@ pub struct Input {
     pub key: String,
 
     pub max: usize,
+
+    /// Search the dedicated HK securities list instead of the mixed endpoint
+    pub hk: bool,
 }
 
 impl Default for Input {
@@ -33,27 +94,111 @@ impl Default for Input {
         Self {
             key: Default::default(),
             max: 10,
+            hk: false,
         }
     }
 }
 
+impl Input {
+    pub fn new(keyword: impl Into<String>) -> Self {
+        Self::default().with_keyword(keyword)
+    }
+
+    pub fn with_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.key = keyword.into();
+        self
+    }
+
+    /// Set the max number of results returned by cninfo
+    pub fn with_max(mut self, max: usize) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Search the dedicated HK securities list
+    pub fn with_hk(mut self, hk: bool) -> Self {
+        self.hk = hk;
+        self
+    }
+}
+
 impl crate::QueryInput for Input {
     fn set_keyword(&mut self, keyword: String) {
         self.key = keyword;
     }
+
+    fn keyword(&self) -> &str {
+        &self.key
+    }
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Output {
     code: String,
     zwjc: String,
-    #[serde(rename = "type")]
+    #[serde(rename = "type", default)]
     exchange: String,
+    #[serde(rename = "orgId", default)]
+    org_id: String,
+    #[serde(default)]
+    pinyin: String,
+    #[serde(default)]
+    category: String,
+}
+
+impl Output {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn name(&self) -> &str {
+        &self.zwjc
+    }
+
+    /// The security type of cninfo, such as `shj` or `hke`.
+    pub fn exchange(&self) -> &str {
+        &self.exchange
+    }
+
+    /// The organization id used by the cninfo disclosure api.
+    pub fn org_id(&self) -> &str {
+        &self.org_id
+    }
+
+    pub fn pinyin(&self) -> &str {
+        &self.pinyin
+    }
+
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct HkeStockList {
+    #[serde(rename = "stockList")]
+    stock_list: Vec<Output>,
+}
+
+/// The wrapped results of some versions of the topSearch.
+#[derive(Debug, serde::Deserialize)]
+struct KeyBoardList {
+    #[serde(rename = "keyBoardList")]
+    key_board_list: Vec<Output>,
 }
 
 pub const TYPE_SHJ: &str = "shj";
 pub const TYPE_HKE: &str = "hke";
 
+pub const SEARCH_PATH: &str = "/new/information/topSearch/query";
+
+pub const HKE_STOCK_PATH: &str = "/new/data/hke_stock.json";
+
+pub const COMPANY_PATH: &str = "/data20/companyOverview/getCompanyIntroduction";
+
+/// The field of the industry classification in the company introduction.
+pub const INDUSTRY_FIELD: &str = "F032V";
+
 impl TryFrom<Output> for Stock {
     type Error = color_eyre::Report;
 
@@ -63,11 +208,7 @@ impl TryFrom<Output> for Stock {
             _ => Exchange::guess_from_stock(&value.code),
         };
 
-        Ok(Stock {
-            name: value.zwjc,
-            code: value.code,
-            exchange: exchange?,
-        })
+        Ok(Stock::new(value.zwjc, value.code, exchange?))
     }
 }
 
@@ -75,16 +216,92 @@ impl crate::Search for CnInfo {
     type Input = Input;
     type Output = Output;
 
+    async fn healthcheck(&self) -> color_eyre::Result<()> {
+        crate::probe::check(&self.reqwest, HOMEPAGE, "cninfo").await
+    }
+
+    async fn warm_up(&self) -> color_eyre::Result<()> {
+        crate::probe::warm_up(&self.reqwest, HOMEPAGE, "cninfo").await
+    }
+
     async fn search_all(&self, info: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
-        let url = "https://www.cninfo.com.cn/new/information/topSearch/query";
+        if info.hk {
+            let url = format!("{}{HKE_STOCK_PATH}", self.base_url);
+            let res = self.reqwest.get(url).send().await?;
+            let text = crate::response_text(res, "cninfo").await?;
+
+            crate::dump_response(self.dump_dir.as_deref(), "cninfo", &info.key, &text)?;
+            return parse_hke(&text, &info.key, info.max);
+        }
+
+        let url = format!("{}{SEARCH_PATH}", self.base_url);
         let builder = self
             .reqwest
             .post(url)
             .query(&[("keyWord", &info.key), ("maxNum", &info.max.to_string())]);
 
         let res = builder.send().await?;
-        let text = res.text().await?;
+        let text = crate::response_text(res, "cninfo").await?;
+
+        crate::dump_response(self.dump_dir.as_deref(), "cninfo", &info.key, &text)?;
+        parse(&text)
+    }
+
+    async fn enrich(&self, stock: &mut Stock) -> color_eyre::Result<()> {
+        let builder = self
+            .reqwest
+            .get(format!("{}{COMPANY_PATH}", self.base_url))
+            .query(&[("scode", &stock.code)]);
+        let res = builder.send().await?;
+        let text = crate::response_text(res, "cninfo").await?;
 
-        Ok(serde_json::from_str(&text)?)
+        crate::dump_response(self.dump_dir.as_deref(), "cninfo", &stock.code, &text)?;
+        stock.industry = parse_industry(&text)?;
+        Ok(())
     }
 }
+
+/// Parse the json array returned by cninfo, the array wrapped in the
+/// `{"keyBoardList":[...]}` object is also accepted.
+pub fn parse(text: &str) -> color_eyre::Result<Vec<Output>> {
+    match serde_json::from_str(text) {
+        Ok(outputs) => Ok(outputs),
+        Err(e) => match serde_json::from_str::<KeyBoardList>(text) {
+            Ok(list) => Ok(list.key_board_list),
+            // report the error of the usual shape
+            Err(_) => Err(e.into()),
+        },
+    }
+}
+
+/// Parse the HK securities list returned by cninfo, keep at most `max` items
+/// matching `key`, exact matches go first.
+pub fn parse_hke(text: &str, key: &str, max: usize) -> color_eyre::Result<Vec<Output>> {
+    let list: HkeStockList = serde_json::from_str(text)?;
+    let mut outputs: Vec<Output> = list
+        .stock_list
+        .into_iter()
+        .filter(|v| v.code == key || v.zwjc.contains(key))
+        .map(|mut v| {
+            v.exchange = TYPE_HKE.to_string();
+            v
+        })
+        .collect();
+
+    outputs.sort_by_key(|v| v.code != key && v.zwjc != key);
+    outputs.truncate(max);
+    Ok(outputs)
+}
+
+/// Parse the industry from the company introduction returned by cninfo.
+pub fn parse_industry(text: &str) -> color_eyre::Result<Option<String>> {
+    let value: serde_json::Value = serde_json::from_str(text)?;
+
+    Ok(value
+        .pointer("/data/records/0/basicInformation/0")
+        .and_then(|v| v.get(INDUSTRY_FIELD))
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(String::from))
+}
@@ -0,0 +1,66 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::{Exchange, SecurityKind, Stock};
+
+/// The authoritative set of the Stock Connect (沪港通/深港通) eligible codes,
+/// such as the lists published by the exchanges.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectList {
+    codes: HashSet<String>,
+}
+
+impl ConnectList {
+    /// Load the codes from the file, one per line in any of the styles of
+    /// [`crate::block::parse_code`], see [`ConnectList::parse`].
+    pub fn load(path: &Path) -> color_eyre::Result<Self> {
+        Ok(Self::from_stocks(&crate::block::read(path)?))
+    }
+
+    /// Parse the codes, the empty lines and the lines start with `#` are skipped.
+    pub fn parse(text: &str) -> color_eyre::Result<Self> {
+        Ok(Self::from_stocks(&crate::block::parse(text)?))
+    }
+
+    pub fn from_stocks(stocks: &[Stock]) -> Self {
+        Self {
+            codes: stocks.iter().map(Stock::normalize).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    pub fn contains(&self, stock: &Stock) -> bool {
+        self.codes.contains(&stock.normalize())
+    }
+
+    /// Set [`Stock::connect_eligible`] of the stocks, the stocks not in the
+    /// list are not eligible.
+    pub fn apply(&self, stocks: &mut [Stock]) {
+        for stock in stocks {
+            stock.connect_eligible = Some(self.contains(stock));
+        }
+    }
+}
+
+/// Guess the Stock Connect eligibility without a [`ConnectList`].
+///
+/// The real eligibility depends on the index membership, the market value and
+/// the trading status which change each review, so the heuristic can only
+/// rule out the obvious cases: the Beijing and US stocks, the indexes and the
+/// risk warning stocks such as `ST` and `*ST`. It returns `None` for the
+/// others, supply a [`ConnectList`] for the authoritative answer.
+pub fn guess_eligible(stock: &Stock) -> Option<bool> {
+    let risk_warning = ["ST", "*ST", "S*ST", "SST"]
+        .iter()
+        .any(|v| stock.name.starts_with(v));
+
+    match stock.exchange {
+        Exchange::BeiJing | Exchange::UnitedStates => Some(false),
+        _ if stock.kind == SecurityKind::Index => Some(false),
+        v if v.is_mainland() && risk_warning => Some(false),
+        _ => None,
+    }
+}
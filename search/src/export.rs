@@ -0,0 +1,460 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::Exchange;
+use crate::NormalizeStyle;
+use crate::Stock;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Normalized codes of 通达信, see [`ebk`]
+    #[default]
+    Ebk,
+    Csv,
+    /// Stock names, one per line
+    TxtNames,
+    Json,
+    /// One json object per line, see [`ndjson_line`]
+    Ndjson,
+    /// Aligned columns for the terminal, see [`table`]
+    Table,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 6] = [
+        ExportFormat::Ebk,
+        ExportFormat::Csv,
+        ExportFormat::TxtNames,
+        ExportFormat::Json,
+        ExportFormat::Ndjson,
+        ExportFormat::Table,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Ebk => "ebk",
+            ExportFormat::Csv => "csv",
+            ExportFormat::TxtNames => "txt-names",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Table => "table",
+        }
+    }
+
+    /// The default file extension of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Ebk => "ebk",
+            ExportFormat::Csv => "csv",
+            ExportFormat::TxtNames => "txt",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Table => "txt",
+        }
+    }
+
+    /// Prepend the `# comment` line to the exported content. The ebk is kept
+    /// as is since 通达信 may not accept it, so are the json formats, json
+    /// has no comment syntax.
+    pub fn with_comment(&self, content: String, comment: &str) -> String {
+        match self {
+            ExportFormat::Csv | ExportFormat::TxtNames | ExportFormat::Table => {
+                format!("# {comment}\n{content}")
+            }
+            ExportFormat::Ebk | ExportFormat::Json | ExportFormat::Ndjson => content,
+        }
+    }
+
+    pub fn export(&self, stocks: &[Stock]) -> color_eyre::Result<String> {
+        self.export_with(stocks, NormalizeStyle::default())
+    }
+
+    /// Export the stocks, the normalized codes are written in the `style`.
+    pub fn export_with(
+        &self,
+        stocks: &[Stock],
+        style: NormalizeStyle,
+    ) -> color_eyre::Result<String> {
+        match self {
+            ExportFormat::Ebk => Ok(ebk_with(stocks, style)),
+            ExportFormat::Csv => Ok(csv_with(stocks, style)),
+            ExportFormat::TxtNames => Ok(txt_names(stocks)),
+            ExportFormat::Json => json(stocks),
+            ExportFormat::Ndjson => ndjson(stocks),
+            ExportFormat::Table => Ok(table_with(stocks, style)),
+        }
+    }
+
+    /// Export the results of the keywords in the input order, a keyword found
+    /// nothing is written as a placeholder line such as `# 平安银行: not found`,
+    /// so the output lines still align with the input. The ndjson format writes
+    /// the [`ndjson_error`] line instead, the json and table formats have no
+    /// placeholder lines.
+    pub fn export_with_placeholders(
+        &self,
+        results: &[(String, Vec<Stock>)],
+        style: NormalizeStyle,
+    ) -> color_eyre::Result<String> {
+        let (mut content, eol) = match self {
+            ExportFormat::Ebk => (String::from(EBK_HEADER), "\r\n"),
+            ExportFormat::Csv => (String::from(CSV_HEADER), "\n"),
+            ExportFormat::TxtNames | ExportFormat::Ndjson => (String::default(), "\n"),
+            ExportFormat::Json | ExportFormat::Table => {
+                return Err(color_eyre::eyre::eyre!(
+                    "The {} format does not support the placeholder lines",
+                    self.name()
+                ));
+            }
+        };
+
+        for (keyword, stocks) in results {
+            if !stocks.is_empty() {
+                let exported = self.export_with(stocks, style)?;
+                // the header is already written
+                let lines = match self {
+                    ExportFormat::Ebk => exported.strip_prefix(EBK_HEADER),
+                    ExportFormat::Csv => exported.strip_prefix(CSV_HEADER),
+                    _ => None,
+                };
+
+                content.push_str(lines.unwrap_or(&exported));
+            } else if *self == ExportFormat::Ndjson {
+                let error = color_eyre::eyre::eyre!("not found");
+
+                content.push_str(&ndjson_error(keyword, &error));
+                content.push_str(eol);
+            } else {
+                content.push_str(&format!("# {keyword}: not found{eol}"));
+            }
+        }
+        Ok(content)
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|v| v.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                color_eyre::eyre::eyre!(
+                    "Not a valid format `{s}`, expect one of ebk, csv, txt-names, json, ndjson, table"
+                )
+            })
+    }
+}
+
+/// What to output for the keywords found nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnMissing {
+    /// Fail without writing the output
+    Error,
+    /// Leave out the keywords
+    #[default]
+    Skip,
+    /// Write a placeholder line, see [`ExportFormat::export_with_placeholders`]
+    Placeholder,
+}
+
+impl FromStr for OnMissing {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(OnMissing::Error),
+            "skip" => Ok(OnMissing::Skip),
+            "placeholder" => Ok(OnMissing::Placeholder),
+            _ => Err(color_eyre::eyre::eyre!(
+                "Not a valid policy `{s}`, expect one of error, skip, placeholder"
+            )),
+        }
+    }
+}
+
+/// The encoding of the written output, some 通达信 and 同花顺 installs expect
+/// GBK and show the names of UTF-8 files garbled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputEncoding {
+    #[default]
+    Utf8,
+    Gbk,
+}
+
+impl OutputEncoding {
+    pub const ALL: [OutputEncoding; 2] = [OutputEncoding::Utf8, OutputEncoding::Gbk];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputEncoding::Utf8 => "utf8",
+            OutputEncoding::Gbk => "gbk",
+        }
+    }
+
+    /// Encode the exported content, fail if a character can not be encoded in GBK.
+    pub fn encode(&self, content: &str) -> color_eyre::Result<Vec<u8>> {
+        match self {
+            OutputEncoding::Utf8 => Ok(content.as_bytes().to_vec()),
+            OutputEncoding::Gbk => {
+                let (bytes, _, had_errors) = encoding_rs::GBK.encode(content);
+
+                if had_errors {
+                    return Err(color_eyre::eyre::eyre!(
+                        "Some characters of the output can not be encoded in GBK"
+                    ));
+                }
+                Ok(bytes.into_owned())
+            }
+        }
+    }
+
+    /// Decode the content written in the encoding, such as the existing file in
+    /// the append mode.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            OutputEncoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            OutputEncoding::Gbk => encoding_rs::GBK.decode(bytes).0.into_owned(),
+        }
+    }
+}
+
+impl Display for OutputEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for OutputEncoding {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(OutputEncoding::Utf8),
+            "gbk" => Ok(OutputEncoding::Gbk),
+            _ => Err(color_eyre::eyre::eyre!(
+                "Not a valid encoding `{s}`, expect one of utf8, gbk"
+            )),
+        }
+    }
+}
+
+/// The header line of the `.ebk` file, 通达信 expects an empty first line.
+pub const EBK_HEADER: &str = "\r\n";
+
+/// The comment of [`ExportFormat::with_comment`], such as `generated by
+/// stockconverter at 2024-01-02 09:30:00, tool=cninfo, count=3`, the time is
+/// of Shanghai.
+pub fn generated_comment(tool: &str, count: usize) -> String {
+    let now = Exchange::ShangHai.now();
+
+    format!(
+        "generated by stockconverter at {}, tool={tool}, count={count}",
+        now.format("%Y-%m-%d %H:%M:%S")
+    )
+}
+
+/// Export the `.ebk` block file of 通达信, an empty first line followed by the
+/// normalized codes, each line ends with CRLF. The content is plain ASCII, so
+/// it is same in GBK and UTF-8.
+pub fn ebk(stocks: &[Stock]) -> String {
+    ebk_with(stocks, NormalizeStyle::Tdx)
+}
+
+/// Same as [`ebk`], but write the codes in the `style`, only the
+/// [`NormalizeStyle::Tdx`] can be imported by 通达信.
+pub fn ebk_with(stocks: &[Stock], style: NormalizeStyle) -> String {
+    let mut content = String::from(EBK_HEADER);
+
+    for stock in stocks {
+        content.push_str(&stock.normalize_with(style));
+        content.push_str("\r\n");
+    }
+    content
+}
+
+/// The header line of the csv format.
+pub const CSV_HEADER: &str =
+    "name,code,exchange,normalized,industry,source,query,connect,confidence\n";
+
+pub fn csv(stocks: &[Stock]) -> String {
+    csv_with(stocks, NormalizeStyle::Tdx)
+}
+
+pub fn csv_with(stocks: &[Stock], style: NormalizeStyle) -> String {
+    let mut content = String::from(CSV_HEADER);
+
+    for stock in stocks {
+        content.push_str(&format!(
+            "{},{},{:?},{},{},{},{},{},{}\n",
+            csv_field(&stock.name),
+            csv_field(&stock.code),
+            stock.exchange,
+            stock.normalize_with(style),
+            csv_field(stock.industry.as_deref().unwrap_or_default()),
+            stock.source.map(|v| v.name()).unwrap_or_default(),
+            csv_field(stock.query.as_deref().unwrap_or_default()),
+            stock
+                .connect_eligible
+                .map(|v| v.to_string())
+                .unwrap_or_default(),
+            stock.confidence.map(|v| v.name()).unwrap_or_default()
+        ));
+    }
+    content
+}
+
+pub fn txt_names(stocks: &[Stock]) -> String {
+    let mut content = String::default();
+
+    for stock in stocks {
+        content.push_str(&stock.name);
+        content.push('\n');
+    }
+    content
+}
+
+pub fn json(stocks: &[Stock]) -> color_eyre::Result<String> {
+    Ok(serde_json::to_string_pretty(stocks)?)
+}
+
+pub fn ndjson(stocks: &[Stock]) -> color_eyre::Result<String> {
+    let mut content = String::default();
+
+    for stock in stocks {
+        content.push_str(&ndjson_line(stock)?);
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// The json line of a stock, without the trailing newline.
+pub fn ndjson_line(stock: &Stock) -> color_eyre::Result<String> {
+    Ok(serde_json::to_string(stock)?)
+}
+
+/// The json line of a failed keyword, such as `{"query":"...","error":"..."}`.
+pub fn ndjson_error(query: &str, error: &color_eyre::Report) -> String {
+    serde_json::json!({ "query": query, "error": error.to_string() }).to_string()
+}
+
+/// Export the candidates grouped by the keyword, a line of the keyword and the
+/// count, then the aligned name, code and exchange of each candidate.
+pub fn explore(results: &[(String, Vec<Stock>)]) -> String {
+    let mut content = String::default();
+
+    for (keyword, stocks) in results {
+        let width = stocks
+            .iter()
+            .map(|v| display_width(&v.name))
+            .max()
+            .unwrap_or_default();
+
+        content.push_str(&format!("{keyword} ({})\n", stocks.len()));
+        for stock in stocks {
+            content.push_str(&format!(
+                "  {}{}  {}  {:?}\n",
+                stock.name,
+                " ".repeat(width - display_width(&stock.name)),
+                stock.code,
+                stock.exchange
+            ));
+        }
+    }
+    content
+}
+
+/// Export the name, code, exchange and normalized columns aligned, the CJK
+/// characters take two columns, see [`display_width`]. The query column is
+/// added if any stock keeps the keyword.
+pub fn table(stocks: &[Stock]) -> String {
+    table_with(stocks, NormalizeStyle::Tdx)
+}
+
+pub fn table_with(stocks: &[Stock], style: NormalizeStyle) -> String {
+    let with_query = stocks.iter().any(|v| v.query.is_some());
+    let mut header = vec!["name", "code", "exchange", "normalized"];
+
+    if with_query {
+        header.push("query");
+    }
+    let rows: Vec<Vec<String>> = stocks
+        .iter()
+        .map(|v| {
+            let mut row = vec![
+                v.name.clone(),
+                v.code.clone(),
+                format!("{:?}", v.exchange),
+                v.normalize_with(style),
+            ];
+
+            if with_query {
+                row.push(v.query.clone().unwrap_or_default());
+            }
+            row
+        })
+        .collect();
+    let mut widths: Vec<_> = header.iter().map(|v| display_width(v)).collect();
+
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+
+    let mut content = String::default();
+    let mut push_row = |cells: Vec<&str>| {
+        let mut line = String::default();
+
+        for (index, cell) in cells.iter().enumerate() {
+            line.push_str(cell);
+            if index + 1 < cells.len() {
+                line.push_str(&" ".repeat(widths[index] - display_width(cell) + 2));
+            }
+        }
+        content.push_str(&line);
+        content.push('\n');
+    };
+
+    push_row(header);
+    for row in &rows {
+        push_row(row.iter().map(String::as_str).collect());
+    }
+    content
+}
+
+/// The columns taken by the text in terminal, the wide and full-width
+/// characters of east asian take two columns.
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(|v| if is_wide(v) { 2 } else { 1 }).sum()
+}
+
+fn is_wide(ch: char) -> bool {
+    matches!(ch as u32,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x20000..=0x3FFFD)
+}
+
+fn csv_field(val: &str) -> String {
+    if val.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", val.replace('"', "\"\""))
+    } else {
+        val.to_string()
+    }
+}
@@ -1,23 +1,62 @@
-use color_eyre::eyre::eyre;
+use std::path::PathBuf;
+
 use reqwest::Client;
 
 use crate::{Exchange, Stock};
 
-#[derive(Debug)]
+pub const HOMEPAGE: &str = "https://stock.hexun.com/";
+
+/// The scheme and host of the api.
+pub const BASE_URL: &str = "https://so.hexun.com";
+
+/// The source is `Send + Sync`, cloning it only clones the reference counted
+/// client, the clones share the connection pool and the cookies.
+#[derive(Debug, Clone)]
 pub struct Hexun {
     pub reqwest: Client,
+
+    /// The scheme and host of the api, default is [`BASE_URL`]
+    pub base_url: String,
+
+    /// Write the response bodies to the directory, see [`crate::dump_response`]
+    pub dump_dir: Option<PathBuf>,
 }
 
 impl Hexun {
     pub async fn init(builder: reqwest::ClientBuilder) -> color_eyre::Result<Self> {
-        let reqwest = builder.build()?;
-        let res = reqwest.get("https://stock.hexun.com/").send().await?;
+        Self::init_with_client(crate::decompression(builder).build()?).await
+    }
 
-        if !res.status().is_success() {
-            return Err(eyre!("Can not access hexun website: {}", res.status()));
-        }
+    /// Initialize with a shared client, its connection pool and cookie store are reused.
+    pub async fn init_with_client(reqwest: Client) -> color_eyre::Result<Self> {
+        Self::init_with_probe(reqwest, &crate::probe::Probe::default()).await
+    }
+
+    /// Initialize with a shared client, check the reachability with the `probe`.
+    pub async fn init_with_probe(
+        reqwest: Client,
+        probe: &crate::probe::Probe,
+    ) -> color_eyre::Result<Self> {
+        let tool = Self {
+            reqwest,
+            base_url: BASE_URL.to_string(),
+            dump_dir: None,
+        };
+
+        probe.check(&tool, &tool.reqwest, HOMEPAGE, "hexun").await?;
+
+        Ok(tool)
+    }
+
+    /// Send the requests to the `base_url` instead, such as a mirror or a mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
 
-        Ok(Self { reqwest })
+    pub fn with_dump_dir(mut self, dump_dir: Option<PathBuf>) -> Self {
+        self.dump_dir = dump_dir;
+        self
     }
 }
 
@@ -31,7 +70,8 @@ pub struct Input {
 impl Default for Input {
     fn default() -> Self {
         // 6871526549834742
-        let local = chrono::Local::now();
+        // the time of the site, the time zone of the system may be broken
+        let local = Exchange::ShangHai.now();
         let mut ty = format!("stock?math=0.{}", local.format("%Y%m%d%H%M%S%3f"));
 
         ty.pop();
@@ -42,10 +82,31 @@ impl Default for Input {
     }
 }
 
+impl Input {
+    pub fn new(keyword: impl Into<String>) -> Self {
+        Self::default().with_keyword(keyword)
+    }
+
+    pub fn with_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.key = keyword.into();
+        self
+    }
+
+    /// Set the type parameter, defaults to a stock query with a random number
+    pub fn with_ty(mut self, ty: impl Into<String>) -> Self {
+        self.ty = ty.into();
+        self
+    }
+}
+
 impl crate::QueryInput for Input {
     fn set_keyword(&mut self, keyword: String) {
         self.key = keyword;
     }
+
+    fn keyword(&self) -> &str {
+        &self.key
+    }
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -60,25 +121,19 @@ impl TryFrom<Output> for Stock {
     type Error = color_eyre::Report;
 
     fn try_from(value: Output) -> Result<Self, Self::Error> {
-        let exchange = if value.marketcode == "a" {
-            match value.orgcode.as_str() {
+        let exchange = match value.marketcode.as_str() {
+            "a" => match value.orgcode.as_str() {
                 "SSE" => Ok(Exchange::ShangHai),
                 "SZSE" => Ok(Exchange::ShenZhen),
                 "BJSE" => Ok(Exchange::BeiJing),
                 e => Err(color_eyre::eyre::eyre!("Not support exchange `{}`", e)),
-            }
-        } else {
-            Err(color_eyre::eyre::eyre!(
-                "Not support market `{}`",
-                value.marketcode
-            ))
+            },
+            "hk" => Ok(Exchange::HongKong),
+            "us" => Ok(Exchange::UnitedStates),
+            market => Err(color_eyre::eyre::eyre!("Not support market `{}`", market)),
         };
 
-        Ok(Stock {
-            name: value.name,
-            code: value.code,
-            exchange: exchange?,
-        })
+        Ok(Stock::new(value.name, value.code, exchange?))
     }
 }
 
@@ -86,20 +141,30 @@ impl crate::Search for Hexun {
     type Input = Input;
     type Output = Output;
 
+    async fn healthcheck(&self) -> color_eyre::Result<()> {
+        crate::probe::check(&self.reqwest, HOMEPAGE, "hexun").await
+    }
+
     async fn search_all(&self, info: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
-        let (key, _, _) = encoding_rs::GBK.encode(&info.key);
-        let key = urlencoding::encode_binary(&key);
-        let url = format!("https://so.hexun.com/ajax.do?key={}&type={}", key, info.ty);
+        let key = crate::gbk_urlencode(&info.key);
+        let url = format!("{}/ajax.do?key={}&type={}", self.base_url, key, info.ty);
         let res = self.reqwest.get(url).send().await?;
 
-        let text = res.text().await?;
-
-        let (_, json) = text
-            .split_once("=")
-            .ok_or_else(|| color_eyre::eyre::eyre!("Not a valid return from hexun: {text}"))?;
+        let text = crate::response_text(res, "hexun").await?;
 
-        let outputs: Vec<Output> = serde_json::from_str(json)?;
+        crate::dump_response(self.dump_dir.as_deref(), "hexun", &info.key, &text)?;
 
-        Ok(outputs)
+        parse(&text)
     }
 }
+
+/// Parse the javascript assignment returned by hexun.
+pub fn parse(text: &str) -> color_eyre::Result<Vec<Output>> {
+    let (_, json) = text
+        .split_once("=")
+        .ok_or_else(|| color_eyre::eyre::eyre!("Not a valid return from hexun: {text}"))?;
+
+    let outputs: Vec<Output> = serde_json::from_str(json)?;
+
+    Ok(outputs)
+}
@@ -1,8 +1,20 @@
+pub mod all;
+pub mod batch;
+pub mod block;
 pub mod cfi;
+pub mod chinese;
 pub mod cninfo;
+pub mod connect;
+pub mod export;
 pub mod hexun;
+pub mod limiter;
+pub mod offline;
+pub mod overrides;
+pub mod probe;
+pub mod selftest;
 pub mod sina;
 pub mod sohu;
+pub mod synonyms;
 
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize,
@@ -12,12 +24,323 @@ pub enum Exchange {
     ShenZhen,
     BeiJing,
     HongKong,
+    UnitedStates,
+}
+
+/// The kind of the security.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum SecurityKind {
+    #[default]
+    Stock,
+    /// The index such as 上证指数 `sh000001` and 深证成指 `sz399001`
+    Index,
+}
+
+/// The listing board of a stock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Board {
+    /// The main board of Shanghai or Shenzhen
+    Main,
+    /// The STAR market of Shanghai, `688` and `689`
+    Star,
+    /// The ChiNext market of Shenzhen, see [`CHINEXT_PREFIXES`]
+    ChiNext,
+    /// The Beijing stock exchange
+    BeiJing,
+    HongKong,
+    UnitedStates,
+}
+
+/// Which listing is selected when the candidates are listed in several markets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchPreference {
+    /// The first mainland stock, then the first HK or US stock
+    #[default]
+    AShareFirst,
+    /// The first HK stock, then the first mainland stock, then the first US stock
+    HShareFirst,
+    /// The first candidate
+    FirstAny,
+}
+
+impl std::str::FromStr for MatchPreference {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "a" | "a-share" => Ok(Self::AShareFirst),
+            "h" | "h-share" | "hk" => Ok(Self::HShareFirst),
+            "any" | "first" => Ok(Self::FirstAny),
+            _ => Err(color_eyre::eyre::eyre!(
+                "Not a valid preference `{s}`, expect one of a, h, any"
+            )),
+        }
+    }
+}
+
+/// The script of the chinese names, see [`SearchOptions::hk_script`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script {
+    Traditional,
+    Simplified,
+}
+
+impl Script {
+    /// Convert the characters of `val` to the script.
+    pub fn convert(&self, val: &str) -> String {
+        match self {
+            Script::Traditional => chinese::traditionalize(val),
+            Script::Simplified => chinese::simplify(val),
+        }
+    }
+}
+
+impl std::str::FromStr for Script {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "trad" | "traditional" => Ok(Self::Traditional),
+            "simp" | "simplified" => Ok(Self::Simplified),
+            _ => Err(color_eyre::eyre::eyre!(
+                "Not a valid script `{s}`, expect one of trad, simp"
+            )),
+        }
+    }
+}
+
+/// How much the resolved stock can be trusted, from how its name matches the keyword.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    /// The name does not contain the keyword, such as a code, an abbreviation
+    /// or a guessed exchange
+    Low,
+    /// The name starts with the keyword, or the keyword starts with the name
+    Medium,
+    /// The name or one of the aliases is the keyword
+    High,
+}
+
+impl Confidence {
+    /// Grade the stock resolved from the keyword.
+    pub fn of(keyword: &str, stock: &Stock) -> Self {
+        let keyword = keyword.trim();
+        let names = || std::iter::once(&stock.name).chain(stock.aliases.iter());
+
+        if keyword.is_empty() || stock.name.is_empty() {
+            Self::Low
+        } else if names().any(|v| v.eq_ignore_ascii_case(keyword)) {
+            Self::High
+        } else if names().any(|v| v.starts_with(keyword) || keyword.starts_with(v.as_str())) {
+            Self::Medium
+        } else {
+            Self::Low
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        }
+    }
+}
+
+impl std::fmt::Display for Confidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How the normalized code is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NormalizeStyle {
+    /// The market digit of 通达信 before the code, such as `1600519`
+    #[default]
+    Tdx,
+    /// The lowercase exchange before the code, such as `sh600519`
+    Prefix,
+    /// The uppercase exchange after the code, such as `600519.SH`
+    Suffix,
+    /// The code only, such as `600519`
+    Plain,
+    /// The suffix of Yahoo Finance, such as `600519.SS` and `0700.HK`
+    Yahoo,
+}
+
+impl NormalizeStyle {
+    pub const ALL: [NormalizeStyle; 5] = [
+        NormalizeStyle::Tdx,
+        NormalizeStyle::Prefix,
+        NormalizeStyle::Suffix,
+        NormalizeStyle::Plain,
+        NormalizeStyle::Yahoo,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NormalizeStyle::Tdx => "tdx",
+            NormalizeStyle::Prefix => "prefix",
+            NormalizeStyle::Suffix => "suffix",
+            NormalizeStyle::Plain => "plain",
+            NormalizeStyle::Yahoo => "yahoo",
+        }
+    }
+
+    pub fn format(&self, exchange: Exchange, code: &str) -> String {
+        // the padded code of hong kong is shared by all the styles
+        let code = match exchange {
+            Exchange::HongKong => pad_hk_code(code).unwrap_or_else(|| code.to_string()),
+            Exchange::UnitedStates => code.to_ascii_uppercase(),
+            _ => code.to_string(),
+        };
+
+        match self {
+            NormalizeStyle::Tdx => exchange.format(&code),
+            NormalizeStyle::Prefix => format!("{}{code}", exchange.short_name()),
+            NormalizeStyle::Suffix => {
+                format!("{code}.{}", exchange.short_name().to_ascii_uppercase())
+            }
+            NormalizeStyle::Plain => code,
+            NormalizeStyle::Yahoo => match exchange {
+                Exchange::ShangHai => format!("{code}.SS"),
+                Exchange::ShenZhen => format!("{code}.SZ"),
+                Exchange::BeiJing => format!("{code}.BJ"),
+                // yahoo uses 4 digits for the hong kong codes under 10000
+                Exchange::HongKong => format!("{}.HK", code.strip_prefix('0').unwrap_or(&code)),
+                Exchange::UnitedStates => code,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for NormalizeStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl std::str::FromStr for NormalizeStyle {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|v| v.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                color_eyre::eyre::eyre!(
+                    "Not a valid style `{s}`, expect one of tdx, prefix, suffix, plain, yahoo"
+                )
+            })
+    }
+}
+
+/// The code prefixes of ChiNext, `300` is the original range, `301` is issued
+/// under the registration system, `302` and `303` are reserved for later issues.
+pub const CHINEXT_PREFIXES: [&str; 4] = ["300", "301", "302", "303"];
+
+/// A code prefix of the mainland exchanges and its meaning, see [`code_prefixes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodePrefix {
+    pub prefix: &'static str,
+
+    pub exchange: Exchange,
+
+    pub board: Board,
+
+    pub kind: SecurityKind,
+}
+
+impl CodePrefix {
+    const fn new(
+        prefix: &'static str,
+        exchange: Exchange,
+        board: Board,
+        kind: SecurityKind,
+    ) -> Self {
+        Self {
+            prefix,
+            exchange,
+            board,
+            kind,
+        }
+    }
+}
+
+// the 2-digit prefixes are the valid codes, the longer ones of the boards
+// win in `find_prefix`, the rest of them are the main board
+const CODE_PREFIXES: [CodePrefix; 17] = [
+    CodePrefix::new("60", Exchange::ShangHai, Board::Main, SecurityKind::Stock),
+    CodePrefix::new("68", Exchange::ShangHai, Board::Main, SecurityKind::Stock),
+    CodePrefix::new("688", Exchange::ShangHai, Board::Star, SecurityKind::Stock),
+    CodePrefix::new("689", Exchange::ShangHai, Board::Star, SecurityKind::Stock),
+    CodePrefix::new("00", Exchange::ShenZhen, Board::Main, SecurityKind::Stock),
+    CodePrefix::new("30", Exchange::ShenZhen, Board::Main, SecurityKind::Stock),
+    chinext_prefix(0),
+    chinext_prefix(1),
+    chinext_prefix(2),
+    chinext_prefix(3),
+    CodePrefix::new("88", Exchange::BeiJing, Board::BeiJing, SecurityKind::Stock),
+    CodePrefix::new("87", Exchange::BeiJing, Board::BeiJing, SecurityKind::Stock),
+    CodePrefix::new("83", Exchange::BeiJing, Board::BeiJing, SecurityKind::Stock),
+    CodePrefix::new("43", Exchange::BeiJing, Board::BeiJing, SecurityKind::Stock),
+    CodePrefix::new("000", Exchange::ShangHai, Board::Main, SecurityKind::Index),
+    CodePrefix::new("399", Exchange::ShenZhen, Board::Main, SecurityKind::Index),
+    CodePrefix::new(
+        "899",
+        Exchange::BeiJing,
+        Board::BeiJing,
+        SecurityKind::Index,
+    ),
+];
+
+const fn chinext_prefix(index: usize) -> CodePrefix {
+    CodePrefix::new(
+        CHINEXT_PREFIXES[index],
+        Exchange::ShenZhen,
+        Board::ChiNext,
+        SecurityKind::Stock,
+    )
+}
+
+/// The code prefixes of the mainland exchanges, the validation, the board and
+/// the guessing of the exchange are derived from it. The HK and US codes have
+/// no prefix, they are checked by the length and the characters.
+pub fn code_prefixes() -> &'static [CodePrefix] {
+    &CODE_PREFIXES
+}
+
+/// Find the longest prefix of the code in the `exchange` and the `kind`.
+pub fn find_prefix(
+    exchange: Exchange,
+    kind: SecurityKind,
+    code: &str,
+) -> Option<&'static CodePrefix> {
+    CODE_PREFIXES
+        .iter()
+        .filter(|v| v.exchange == exchange && v.kind == kind && code.starts_with(v.prefix))
+        .max_by_key(|v| v.prefix.len())
 }
 
 #[derive(
-    Debug, Clone, Copy, Default, cote::prelude::CoteOpt, cote::prelude::CoteVal, PartialEq, Eq,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    cote::prelude::CoteOpt,
+    cote::prelude::CoteVal,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Deserialize,
+    serde::Serialize,
 )]
 #[coteval(igcase)]
+#[serde(rename_all = "lowercase")]
 pub enum Tool {
     Sina,
     #[default]
@@ -27,8 +350,222 @@ pub enum Tool {
     SoHu,
 }
 
+impl Tool {
+    pub const ALL: [Tool; 5] = [Tool::CnInfo, Tool::Sina, Tool::Cfi, Tool::HeXun, Tool::SoHu];
+
+    /// The name used on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Sina => "sina",
+            Tool::CnInfo => "cninfo",
+            Tool::Cfi => "cfi",
+            Tool::HeXun => "hexun",
+            Tool::SoHu => "sohu",
+        }
+    }
+
+    /// The name of the website.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Tool::Sina => "新浪财经",
+            Tool::CnInfo => "巨潮信息网",
+            Tool::Cfi => "中财网",
+            Tool::HeXun => "和讯网",
+            Tool::SoHu => "搜狐网",
+        }
+    }
+
+    /// The short names accepted besides [`Tool::name`].
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Tool::Sina => &["sn", "sina-finance"],
+            Tool::CnInfo => &["cn", "cni", "juchao"],
+            Tool::Cfi => &["cf", "zcw"],
+            Tool::HeXun => &["hx"],
+            Tool::SoHu => &["so", "souhu"],
+        }
+    }
+
+    pub fn homepage(&self) -> &'static str {
+        match self {
+            Tool::Sina => sina::HOMEPAGE,
+            Tool::CnInfo => cninfo::HOMEPAGE,
+            Tool::Cfi => cfi::HOMEPAGE,
+            Tool::HeXun => hexun::HOMEPAGE,
+            Tool::SoHu => sohu::HOMEPAGE,
+        }
+    }
+}
+
+impl std::str::FromStr for Tool {
+    type Err = color_eyre::Report;
+
+    /// Match the name or the aliases exactly, or an unambiguous prefix of them.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let names = |tool: Tool| std::iter::once(tool.name()).chain(tool.aliases().iter().copied());
+
+        if let Some(tool) = Self::ALL
+            .into_iter()
+            .find(|v| names(*v).any(|name| name == s))
+        {
+            return Ok(tool);
+        }
+        let matched: Vec<_> = Self::ALL
+            .into_iter()
+            .filter(|v| !s.is_empty() && names(*v).any(|name| name.starts_with(&s)))
+            .collect();
+
+        match matched.as_slice() {
+            [tool] => Ok(*tool),
+            [] => Err(color_eyre::eyre::eyre!(
+                "Not a valid tool `{s}`, expect one of cninfo, sina, cfi, hexun, sohu"
+            )),
+            tools => Err(color_eyre::eyre::eyre!(
+                "Ambiguous tool `{s}`, could be {}",
+                tools
+                    .iter()
+                    .map(|v| v.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+impl std::str::FromStr for Exchange {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sh" | "sse" | "shanghai" => Ok(Self::ShangHai),
+            "sz" | "szse" | "shenzhen" => Ok(Self::ShenZhen),
+            "bj" | "bse" | "bjse" | "beijing" => Ok(Self::BeiJing),
+            "hk" | "hkex" | "hongkong" => Ok(Self::HongKong),
+            "us" | "nasdaq" | "nyse" => Ok(Self::UnitedStates),
+            _ => Err(color_eyre::eyre::eyre!(
+                "Not a valid exchange `{s}`, expect one of sh, sz, bj, hk, us"
+            )),
+        }
+    }
+}
+
+impl Format for Exchange {
+    fn format(&self, val: &str) -> String {
+        match self {
+            Exchange::ShangHai => ShangHai.format(val),
+            Exchange::ShenZhen => ShenZhen.format(val),
+            Exchange::BeiJing => BeiJing.format(val),
+            Exchange::HongKong => HongKong.format(val),
+            Exchange::UnitedStates => UnitedStates.format(val),
+        }
+    }
+}
+
+impl Valid for Exchange {
+    fn valid(&self, val: &str) -> Option<()> {
+        match self {
+            Exchange::ShangHai => ShangHai.valid(val),
+            Exchange::ShenZhen => ShenZhen.valid(val),
+            Exchange::BeiJing => BeiJing.valid(val),
+            Exchange::HongKong => HongKong.valid(val),
+            Exchange::UnitedStates => UnitedStates.valid(val),
+        }
+    }
+}
+
 impl Exchange {
+    pub const ALL: [Exchange; 5] = [
+        Exchange::ShangHai,
+        Exchange::ShenZhen,
+        Exchange::BeiJing,
+        Exchange::HongKong,
+        Exchange::UnitedStates,
+    ];
+
+    /// The short name used by the prefix and suffix styles, such as `sh`.
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            Exchange::ShangHai => "sh",
+            Exchange::ShenZhen => "sz",
+            Exchange::BeiJing => "bj",
+            Exchange::HongKong => "hk",
+            Exchange::UnitedStates => "us",
+        }
+    }
+
+    /// The timezone of the trading hours.
+    pub fn timezone(&self) -> chrono_tz::Tz {
+        match self {
+            Exchange::ShangHai | Exchange::ShenZhen | Exchange::BeiJing => {
+                chrono_tz::Asia::Shanghai
+            }
+            Exchange::HongKong => chrono_tz::Asia::Hong_Kong,
+            Exchange::UnitedStates => chrono_tz::America::New_York,
+        }
+    }
+
+    /// The continuous trading sessions in the minutes of the local day, the
+    /// call auctions are not included.
+    fn sessions(&self) -> &'static [(u32, u32)] {
+        match self {
+            // 09:30-11:30, 13:00-15:00
+            Exchange::ShangHai | Exchange::ShenZhen | Exchange::BeiJing => {
+                &[(570, 690), (780, 900)]
+            }
+            // 09:30-12:00, 13:00-16:00
+            Exchange::HongKong => &[(570, 720), (780, 960)],
+            // 09:30-16:00
+            Exchange::UnitedStates => &[(570, 960)],
+        }
+    }
+
+    /// Return true if the `time` is in the trading sessions of the exchange,
+    /// the weekends, the lunch breaks and the `holidays` in the local date are
+    /// not trading.
+    pub fn is_trading_at<Tz: chrono::TimeZone>(
+        &self,
+        time: &chrono::DateTime<Tz>,
+        holidays: &[chrono::NaiveDate],
+    ) -> bool {
+        use chrono::{Datelike, Timelike};
+
+        let local = time.with_timezone(&self.timezone());
+
+        if matches!(local.weekday(), chrono::Weekday::Sat | chrono::Weekday::Sun)
+            || holidays.contains(&local.date_naive())
+        {
+            return false;
+        }
+        let minute = local.hour() * 60 + local.minute();
+
+        self.sessions()
+            .iter()
+            .any(|(open, close)| (*open..*close).contains(&minute))
+    }
+
+    /// The current time of the exchange, it does not depend on the time zone
+    /// of the system, which is missing or broken in some minimal images.
+    pub fn now(&self) -> chrono::DateTime<chrono_tz::Tz> {
+        chrono::Utc::now().with_timezone(&self.timezone())
+    }
+
+    /// Same as [`Exchange::is_trading_at`] with the current time, the holidays
+    /// are ignored.
+    pub fn is_trading_now(&self) -> bool {
+        self.is_trading_at(&chrono::Utc::now(), &[])
+    }
+
+    /// Return true for the exchanges of the mainland A shares.
+    pub fn is_mainland(&self) -> bool {
+        matches!(
+            self,
+            Exchange::ShangHai | Exchange::ShenZhen | Exchange::BeiJing
+        )
+    }
+
     pub fn guess_from_stock(val: &str) -> color_eyre::Result<Exchange> {
+        validate_code_shape(val)?;
         if HongKong.valid(val).is_some() {
             Ok(Self::HongKong)
         } else if ShangHai.valid(val).is_some() {
@@ -41,54 +578,769 @@ impl Exchange {
             Err(color_eyre::eyre::eyre!("Not a valid stock number: {val}"))
         }
     }
+
+    /// Guess the exchange of an index code, the index codes overlap the stock
+    /// codes, such as `000001` is 上证指数 but also 平安银行.
+    pub fn guess_from_index(val: &str) -> color_eyre::Result<Exchange> {
+        CODE_PREFIXES
+            .iter()
+            .find(|v| v.kind == SecurityKind::Index && val.len() == 6 && val.starts_with(v.prefix))
+            .map(|v| v.exchange)
+            .ok_or_else(|| color_eyre::eyre::eyre!("Not a valid index number: {val}"))
+    }
+}
+
+/// The user agent used by the default client.
+pub const USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:140.0) Gecko/20100101 Firefox/140.0";
+
+/// Build the client used when no client is given.
+pub fn default_client() -> color_eyre::Result<reqwest::Client> {
+    Ok(decompression(reqwest::Client::builder())
+        .user_agent(USER_AGENT)
+        .cookie_store(true)
+        .build()?)
+}
+
+/// The timeouts of the client, `None` keeps the default of reqwest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Fail fast on the dead hosts
+    pub connect: Option<std::time::Duration>,
+
+    /// The whole request, include the slow but alive endpoints
+    pub read: Option<std::time::Duration>,
+}
+
+impl Timeouts {
+    pub fn with_connect(mut self, connect: Option<std::time::Duration>) -> Self {
+        self.connect = connect;
+        self
+    }
+
+    pub fn with_read(mut self, read: Option<std::time::Duration>) -> Self {
+        self.read = read;
+        self
+    }
+
+    /// Set the timeouts to [`reqwest::ClientBuilder::connect_timeout`] and
+    /// [`reqwest::ClientBuilder::timeout`].
+    pub fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if let Some(connect) = self.connect {
+            builder = builder.connect_timeout(connect);
+        }
+        if let Some(read) = self.read {
+            builder = builder.timeout(read);
+        }
+        builder
+    }
+}
+
+/// Enable the gzip, brotli and deflate decoding, some of the sources compress
+/// the body depending on the network.
+pub fn decompression(builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+    builder.gzip(true).brotli(true).deflate(true)
+}
+
+/// The bytes of the response shown on each side of the offset by [`parse_error`].
+const SNIPPET_BYTES: usize = 32;
+
+/// Wrap the parse error of the `name` source with the offset and the
+/// surrounding text of the response, such as when the site changed its markup.
+pub fn parse_error(
+    name: &str,
+    text: &str,
+    offset: usize,
+    error: impl std::fmt::Display,
+) -> color_eyre::Report {
+    color_eyre::eyre::eyre!(
+        "Can not parse the response of {name} at offset {offset}: {error}, near {:?}",
+        snippet(text, offset)
+    )
+}
+
+/// The text around the byte offset, adjusted to the character boundaries.
+pub fn snippet(text: &str, offset: usize) -> &str {
+    let mut start = offset.saturating_sub(SNIPPET_BYTES).min(text.len());
+    let mut end = offset.saturating_add(SNIPPET_BYTES).min(text.len());
+
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+    &text[start..end]
+}
+
+/// Parse a header of the `Name: value` syntax, such as `Referer: https://example.com`.
+pub fn parse_header(
+    val: &str,
+) -> color_eyre::Result<(reqwest::header::HeaderName, reqwest::header::HeaderValue)> {
+    let (name, value) = val.split_once(':').ok_or_else(|| {
+        color_eyre::eyre::eyre!("Not a valid header `{val}`, expect `Name: value`")
+    })?;
+    let name = reqwest::header::HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|e| color_eyre::eyre::eyre!("Not a valid header name of `{val}`: {e}"))?;
+    let value = reqwest::header::HeaderValue::from_str(value.trim())
+        .map_err(|e| color_eyre::eyre::eyre!("Not a valid header value of `{val}`: {e}"))?;
+
+    Ok((name, value))
+}
+
+/// Build the extra headers sent with each request, the `cookies` of the `k=v`
+/// syntax are joined into one `Cookie` header. The cookie header replaces the
+/// cookies of the session, such as the ones set by [`Search::warm_up`].
+pub fn extra_headers(
+    headers: &[String],
+    cookies: &[String],
+) -> color_eyre::Result<reqwest::header::HeaderMap> {
+    let mut map = reqwest::header::HeaderMap::new();
+
+    for header in headers {
+        let (name, value) = parse_header(header)?;
+
+        map.append(name, value);
+    }
+    if !cookies.is_empty() {
+        for cookie in cookies {
+            match cookie.split_once('=') {
+                Some((name, _)) if !name.trim().is_empty() => {}
+                _ => {
+                    return Err(color_eyre::eyre::eyre!(
+                        "Not a valid cookie `{cookie}`, expect `name=value`"
+                    ));
+                }
+            }
+        }
+        let cookie = cookies
+            .iter()
+            .map(|v| v.trim())
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        map.insert(
+            reqwest::header::COOKIE,
+            reqwest::header::HeaderValue::from_str(&cookie)
+                .map_err(|e| color_eyre::eyre::eyre!("Not a valid cookie `{cookie}`: {e}"))?,
+        );
+    }
+    Ok(map)
+}
+
+/// Read the body as text, return an error if the body is still compressed,
+/// such as the client is built without [`decompression`].
+pub async fn response_text(res: reqwest::Response, name: &str) -> color_eyre::Result<String> {
+    // the header is removed after the body is decoded
+    if let Some(encoding) = res
+        .headers()
+        .get(reqwest::header::CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.eq_ignore_ascii_case("identity"))
+    {
+        return Err(color_eyre::eyre::eyre!(
+            "The {encoding} compressed body of {name} is not decoded"
+        ));
+    }
+    let text = res.text().await?;
+
+    // the magic number of gzip
+    if text.starts_with('\u{1f}') {
+        return Err(color_eyre::eyre::eyre!(
+            "The body of {name} looks compressed but is not decoded"
+        ));
+    }
+    Ok(text)
+}
+
+/// Write the response `text` of the source `name` to the `dir` before it is
+/// parsed, nothing is written if `dir` is `None`. The file is named by
+/// [`dump_file_name`], the later response of the same keyword replaces it.
+pub fn dump_response(
+    dir: Option<&std::path::Path>,
+    name: &str,
+    keyword: &str,
+    text: &str,
+) -> color_eyre::Result<()> {
+    let Some(dir) = dir else {
+        return Ok(());
+    };
+    let path = dir.join(dump_file_name(name, keyword));
+
+    std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&path, text))
+        .map_err(|e| {
+            color_eyre::eyre::eyre!(
+                "Can not dump the response of {name} to {}: {e}",
+                path.display()
+            )
+        })
+}
+
+/// The file name of the response, such as `贵州茅台.sina.txt`. The characters
+/// not allowed in the file names, such as `/`, are replaced with `_`.
+pub fn dump_file_name(name: &str, keyword: &str) -> String {
+    let keyword: String = keyword
+        .trim()
+        .chars()
+        .take(MAX_KEYWORD_CHARS)
+        .map(|v| match v {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '.' => '_',
+            v if v.is_control() || v.is_whitespace() => '_',
+            v => v,
+        })
+        .collect();
+    let keyword = if keyword.is_empty() { "_" } else { &keyword };
+
+    format!("{keyword}.{name}.txt")
+}
+
+/// Resolve a single keyword with the tool, pass the `client` to reuse its
+/// connection pool and cookies between calls.
+pub async fn resolve(
+    keyword: &str,
+    tool: Tool,
+    client: Option<reqwest::Client>,
+) -> color_eyre::Result<Stock> {
+    async fn search_one<T>(tool: T, keyword: &str, name: Tool) -> color_eyre::Result<Stock>
+    where
+        T: Search,
+        T::Input: Default,
+    {
+        let mut input = <T::Input>::default();
+
+        tool.warm_up().await?;
+        input.set_keyword(keyword.to_string());
+        tool.search(&input).await.map(|mut v| {
+            v.source = Some(name);
+            v
+        })
+    }
+
+    let client = client.map_or_else(default_client, Ok)?;
+
+    match tool {
+        Tool::Sina => search_one(sina::Sina::init_with_client(client).await?, keyword, tool).await,
+        Tool::CnInfo => {
+            search_one(
+                cninfo::CnInfo::init_with_client(client).await?,
+                keyword,
+                tool,
+            )
+            .await
+        }
+        Tool::Cfi => search_one(cfi::Cfi::init_with_client(client).await?, keyword, tool).await,
+        Tool::HeXun => {
+            search_one(hexun::Hexun::init_with_client(client).await?, keyword, tool).await
+        }
+        Tool::SoHu => search_one(sohu::SoHu::init_with_client(client).await?, keyword, tool).await,
+    }
 }
 
 pub trait Search {
     type Input: QueryInput;
-    type Output: TryInto<Stock>;
+    type Output: TryInto<Stock, Error: std::fmt::Display>;
 
     fn search_all(
         &self,
         input: &Self::Input,
     ) -> impl Future<Output = color_eyre::Result<Vec<Self::Output>>>;
 
+    /// Check the source is still reachable, the instance can be re-validated
+    /// without reconstruction.
+    fn healthcheck(&self) -> impl Future<Output = color_eyre::Result<()>> {
+        async { Ok(()) }
+    }
+
+    /// Prime the session before the first search, such as fetching the cookies
+    /// of the homepage. It is called once by the binaries before the keyword loop.
+    ///
+    /// The cninfo and cfi sources implement it, the others need no session.
+    fn warm_up(&self) -> impl Future<Output = color_eyre::Result<()>> {
+        async { Ok(()) }
+    }
+
     fn search(&self, input: &Self::Input) -> impl Future<Output = color_eyre::Result<Stock>> {
+        async {
+            self.search_with_options(input, &SearchOptions::default())
+                .await
+        }
+    }
+
+    fn search_with_options(
+        &self,
+        input: &Self::Input,
+        options: &SearchOptions,
+    ) -> impl Future<Output = color_eyre::Result<Stock>> {
+        async {
+            let candidates = self.search_candidates(input, options).await?;
+
+            options.select_or_err(candidates)
+        }
+    }
+
+    /// Search all the stocks accepted by the options, ordered by
+    /// [`SearchOptions::rank`], the candidates not matching the keyword keep the
+    /// order of the source.
+    fn search_candidates(
+        &self,
+        input: &Self::Input,
+        options: &SearchOptions,
+    ) -> impl Future<Output = color_eyre::Result<Vec<Stock>>> {
+        async {
+            options.check_keyword(input.keyword())?;
+
+            let outputs = options.truncate(self.search_all(input).await?);
+            let candidates = accept_outputs(outputs, options)?;
+
+            Ok(options.rank(input.keyword(), candidates))
+        }
+    }
+
+    /// Fill the extra metadata of the stock, such as the industry, it costs an
+    /// extra request for the sources support it.
+    fn enrich(&self, _stock: &mut Stock) -> impl Future<Output = color_eyre::Result<()>> {
+        async { Ok(()) }
+    }
+
+    /// Search both the A share and the H share listing of a dual-listed company.
+    fn search_ah(&self, input: &Self::Input) -> impl Future<Output = color_eyre::Result<AhShare>> {
         async {
             let outputs = self.search_all(input).await?;
-            let mut hongkong = None;
+            let mut ah = AhShare::default();
 
             for output in outputs {
                 let stock: Result<Stock, _> = output.try_into();
 
                 if let Ok(stock) = stock {
-                    if matches!(stock.exchange, Exchange::HongKong) {
-                        if hongkong.is_none() {
-                            hongkong = Some(stock);
-                        }
-                    } else {
-                        return Ok(stock);
+                    let share = match stock.exchange {
+                        Exchange::HongKong => &mut ah.h_share,
+                        exchange if exchange.is_mainland() => &mut ah.a_share,
+                        _ => continue,
+                    };
+
+                    if share.is_none() {
+                        *share = Some(stock);
                     }
                 }
             }
 
-            hongkong.ok_or_else(|| {
-                color_eyre::eyre::eyre!("Can not find valid stock number in results")
-            })
+            if ah.a_share.is_none() && ah.h_share.is_none() {
+                Err(color_eyre::eyre::eyre!(
+                    "Can not find valid stock number in results"
+                ))
+            } else {
+                Ok(ah)
+            }
+        }
+    }
+}
+
+/// The most conversion errors shown by [`convert_outputs`].
+const MAX_CONVERSION_ERRORS: usize = 3;
+
+/// Convert the outputs by `convert`, which returns `None` for the items not
+/// accepted. The outputs failed to convert are skipped, but if none of the
+/// outputs is converted the first errors are returned, a broken conversion
+/// should not look like "no results".
+pub fn convert_outputs<T, U, E>(
+    outputs: impl IntoIterator<Item = T>,
+    mut convert: impl FnMut(T) -> Result<Option<U>, E>,
+) -> color_eyre::Result<Vec<U>>
+where
+    E: std::fmt::Display,
+{
+    let mut converted = 0;
+    let mut errors = ConversionErrors::default();
+    let mut items = vec![];
+
+    for output in outputs {
+        match convert(output) {
+            Ok(item) => {
+                converted += 1;
+                items.extend(item);
+            }
+            Err(e) => errors.push(e),
         }
     }
+    if converted == 0 {
+        errors.into_result()?;
+    }
+    Ok(items)
+}
+
+/// Convert the outputs and apply the options, see [`convert_outputs`].
+pub fn accept_outputs<T>(outputs: Vec<T>, options: &SearchOptions) -> color_eyre::Result<Vec<Stock>>
+where
+    T: TryInto<Stock, Error: std::fmt::Display>,
+{
+    convert_outputs(outputs, |v| v.try_into().map(|v| options.accept(v)))
+}
+
+/// Collect the first errors of the conversions, see [`convert_outputs`].
+#[derive(Debug, Default)]
+pub struct ConversionErrors {
+    count: usize,
+    messages: Vec<String>,
+}
+
+impl ConversionErrors {
+    pub fn push(&mut self, error: impl std::fmt::Display) {
+        self.count += 1;
+        if self.messages.len() < MAX_CONVERSION_ERRORS {
+            self.messages.push(error.to_string());
+        }
+    }
+
+    /// Return an error with the first messages if any conversion failed.
+    pub fn into_result(self) -> color_eyre::Result<()> {
+        if self.count == 0 {
+            return Ok(());
+        }
+        Err(color_eyre::eyre::eyre!(
+            "All the {} results failed to convert: {}",
+            self.count,
+            self.messages.join("; ")
+        ))
+    }
+}
+
+/// The future of [`DynSearch`].
+pub type SearchFuture<'a, T> = std::pin::Pin<Box<dyn Future<Output = color_eyre::Result<T>> + 'a>>;
+
+/// The object safe version of [`Search`], it is implemented for all the
+/// sources with a default input, the heterogeneous sources can be stored as
+/// `Box<dyn DynSearch>`.
+pub trait DynSearch {
+    fn warm_up_dyn(&self) -> SearchFuture<'_, ()>;
+
+    /// Search the keyword with the default input, same as [`Search::search`].
+    fn search_dyn<'a>(&'a self, keyword: &'a str) -> SearchFuture<'a, Stock>;
+
+    /// Search the candidates of the keyword, same as [`Search::search_candidates`].
+    fn search_candidates_dyn<'a>(
+        &'a self,
+        keyword: &'a str,
+        options: &'a SearchOptions,
+    ) -> SearchFuture<'a, Vec<Stock>>;
+}
+
+impl<T> DynSearch for T
+where
+    T: Search,
+    T::Input: Default,
+{
+    fn warm_up_dyn(&self) -> SearchFuture<'_, ()> {
+        Box::pin(self.warm_up())
+    }
+
+    fn search_dyn<'a>(&'a self, keyword: &'a str) -> SearchFuture<'a, Stock> {
+        Box::pin(async move {
+            let mut input = T::Input::default();
+
+            input.set_keyword(keyword.to_string());
+            self.search(&input).await
+        })
+    }
+
+    fn search_candidates_dyn<'a>(
+        &'a self,
+        keyword: &'a str,
+        options: &'a SearchOptions,
+    ) -> SearchFuture<'a, Vec<Stock>> {
+        Box::pin(async move {
+            let mut input = T::Input::default();
+
+            input.set_keyword(keyword.to_string());
+            self.search_candidates(&input, options).await
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AhShare {
+    pub a_share: Option<Stock>,
+    pub h_share: Option<Stock>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Only accept the stocks of the exchange, the ambiguous mainland codes
+    /// valid in it are resolved to it.
+    pub exchange_hint: Option<Exchange>,
+
+    /// Only process the first results returned by the source.
+    pub limit: Option<usize>,
+
+    /// Only accept the securities of the kind.
+    pub kind: Option<SecurityKind>,
+
+    /// Only accept the stocks of the exchanges, empty means all.
+    pub allowed_exchanges: Vec<Exchange>,
+
+    /// Move the candidates whose name or aliases equal the keyword to the front.
+    pub also_match_aliases: bool,
+
+    /// The exchange overrides applied before the other options.
+    pub overrides: overrides::Overrides,
+
+    /// Which listing is selected from the candidates.
+    pub prefer: MatchPreference,
+
+    /// The priority of the exchanges in [`SearchOptions::rank`], such as
+    /// prefer Shanghai to Shenzhen, the exchanges not in it follow in the
+    /// default order. It does not override the [`SearchOptions::prefer`].
+    pub exchange_order: Vec<Exchange>,
+
+    /// The most characters of a keyword, `None` is [`MAX_KEYWORD_CHARS`], see
+    /// [`SearchOptions::check_keyword`].
+    pub max_keyword_chars: Option<usize>,
+
+    /// Convert the names of the HK stocks to the script, some sources return
+    /// the traditional names. The overridden names are kept.
+    pub hk_script: Option<Script>,
+}
+
+/// The default limit of the keyword length, the names of the securities are
+/// far shorter, a longer keyword is usually a pasted paragraph.
+pub const MAX_KEYWORD_CHARS: usize = 64;
+
+impl SearchOptions {
+    pub fn with_exchange_hint(mut self, exchange_hint: Option<Exchange>) -> Self {
+        self.exchange_hint = exchange_hint;
+        self
+    }
+
+    pub fn with_limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn with_allowed_exchanges(mut self, allowed_exchanges: Vec<Exchange>) -> Self {
+        self.allowed_exchanges = allowed_exchanges;
+        self
+    }
+
+    pub fn with_also_match_aliases(mut self, also_match_aliases: bool) -> Self {
+        self.also_match_aliases = also_match_aliases;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: Option<SecurityKind>) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_overrides(mut self, overrides: overrides::Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    pub fn with_prefer(mut self, prefer: MatchPreference) -> Self {
+        self.prefer = prefer;
+        self
+    }
+
+    pub fn with_exchange_order(mut self, exchange_order: Vec<Exchange>) -> Self {
+        self.exchange_order = exchange_order;
+        self
+    }
+
+    pub fn with_max_keyword_chars(mut self, max_keyword_chars: Option<usize>) -> Self {
+        self.max_keyword_chars = max_keyword_chars;
+        self
+    }
+
+    pub fn with_hk_script(mut self, hk_script: Option<Script>) -> Self {
+        self.hk_script = hk_script;
+        self
+    }
+
+    /// Check the keyword before building the request, an overlong keyword makes
+    /// a huge url which some servers reject with `414 URI Too Long`.
+    pub fn check_keyword(&self, keyword: &str) -> color_eyre::Result<()> {
+        let max = self.max_keyword_chars.unwrap_or(MAX_KEYWORD_CHARS);
+        let count = keyword.chars().count();
+
+        if count > max {
+            let head: String = keyword.chars().take(16).collect();
+
+            return Err(color_eyre::eyre::eyre!(
+                "The keyword `{head}...` has {count} characters, more than the limit {max}, is it pasted by mistake?"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Truncate the outputs to the limit, keep the order of the source.
+    pub fn truncate<T>(&self, mut outputs: Vec<T>) -> Vec<T> {
+        if let Some(limit) = self.limit {
+            outputs.truncate(limit);
+        }
+        outputs
+    }
+
+    /// Apply the options to the stock, return `None` if it should be skipped.
+    pub fn accept(&self, mut stock: Stock) -> Option<Stock> {
+        if self.kind.is_some_and(|v| v != stock.kind) {
+            return None;
+        }
+        let overridden = self.overrides.get(&stock.code).is_some();
+
+        self.overrides.apply(&mut stock);
+        if let Some(hint) = self.exchange_hint {
+            if matches!(hint, Exchange::HongKong)
+                && matches!(stock.exchange, Exchange::HongKong)
+                && let Some(code) = pad_hk_code(&stock.code)
+            {
+                stock.code = code;
+            }
+            if !overridden
+                && stock.kind == SecurityKind::Stock
+                && hint.is_mainland()
+                && stock.exchange.is_mainland()
+                && hint.valid(&stock.code).is_some()
+            {
+                stock.exchange = hint;
+            }
+            if stock.exchange != hint {
+                return None;
+            }
+        }
+        if !self.allowed_exchanges.is_empty() && !self.allowed_exchanges.contains(&stock.exchange) {
+            return None;
+        }
+        if let Some(script) = self.hk_script
+            && !overridden
+            && matches!(stock.exchange, Exchange::HongKong)
+        {
+            stock.name = script.convert(&stock.name);
+        }
+
+        Some(stock)
+    }
+
+    /// Return true if the name or one of the aliases of the stock is the keyword.
+    pub fn matches(&self, stock: &Stock, keyword: &str) -> bool {
+        let keyword = keyword.trim();
+
+        stock.name == keyword || stock.aliases.iter().any(|v| v == keyword)
+    }
+
+    /// Order the candidates, the rules in priority are:
+    ///
+    /// 1. the matched candidates with [`SearchOptions::also_match_aliases`]
+    /// 2. the candidates whose name is exactly the keyword
+    ///
+    /// The matched candidates, such as several A shares of the same name, are
+    /// tied by the earlier exchange in [`SearchOptions::exchange_order`], then
+    /// the lower [`Exchange`] such as Shanghai before Shenzhen, then the shorter
+    /// code. The rest keep the order of the source, it is the relevance of the
+    /// source.
+    pub fn rank(&self, keyword: &str, candidates: Vec<Stock>) -> Vec<Stock> {
+        self.rank_by(keyword, candidates, |v| v)
+    }
+
+    /// Same as [`SearchOptions::rank`], but order the items carrying a stock,
+    /// such as the stocks with the records of the source.
+    pub fn rank_by<T>(
+        &self,
+        keyword: &str,
+        mut candidates: Vec<T>,
+        stock: impl Fn(&T) -> &Stock,
+    ) -> Vec<T> {
+        let keyword = keyword.trim();
+
+        // the sort is stable, the candidates of the same key keep the order
+        candidates.sort_by_key(|v| {
+            let v = stock(v);
+            let matched =
+                v.name == keyword || (self.also_match_aliases && self.matches(v, keyword));
+
+            (
+                self.also_match_aliases && !self.matches(v, keyword),
+                v.name != keyword,
+                matched.then(|| (self.exchange_rank(v.exchange), v.code.len())),
+            )
+        });
+        candidates
+    }
+
+    fn exchange_rank(&self, exchange: Exchange) -> (usize, Exchange) {
+        let order = &self.exchange_order;
+
+        (
+            order
+                .iter()
+                .position(|v| *v == exchange)
+                .unwrap_or(order.len()),
+            exchange,
+        )
+    }
+
+    /// Select a stock from the candidates by the [`MatchPreference`], the
+    /// default prefers the first mainland stock, then the first HK or US stock.
+    pub fn select(&self, candidates: Vec<Stock>) -> Option<Stock> {
+        let rank = |stock: &Stock| match self.prefer {
+            MatchPreference::AShareFirst => usize::from(!stock.exchange.is_mainland()),
+            MatchPreference::HShareFirst => match stock.exchange {
+                Exchange::HongKong => 0,
+                v if v.is_mainland() => 1,
+                _ => 2,
+            },
+            MatchPreference::FirstAny => 0,
+        };
+
+        // the first of the stocks with the lowest rank
+        candidates
+            .into_iter()
+            .enumerate()
+            .min_by_key(|(index, stock)| (rank(stock), *index))
+            .map(|(_, stock)| stock)
+    }
+
+    /// Same as [`SearchOptions::select`], return an error if nothing is selected.
+    pub fn select_or_err(&self, candidates: Vec<Stock>) -> color_eyre::Result<Stock> {
+        self.select(candidates)
+            .ok_or_else(|| color_eyre::eyre::eyre!("Can not find valid stock number in results"))
+    }
 }
 
 pub trait QueryInput {
     fn set_keyword(&mut self, keyword: String);
 
+    fn keyword(&self) -> &str;
+
     fn reset(&mut self) {}
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Stock {
     pub name: String,
     pub code: String,
     pub exchange: Exchange,
+    /// The industry classification, filled by [`Search::enrich`].
+    #[serde(default)]
+    pub industry: Option<String>,
+    /// The source resolved the stock, set by the dispatch code.
+    #[serde(default)]
+    pub source: Option<Tool>,
+    #[serde(default)]
+    pub kind: SecurityKind,
+    /// The other names of the stock, such as the historical names.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// The keyword resolved to the stock, kept with [`batch::Batch::keep_query`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// The Stock Connect (沪港通/深港通) eligibility, `None` if unknown, see
+    /// [`connect::ConnectList`] and [`connect::guess_eligible`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_eligible: Option<bool>,
+    /// How the name matches the keyword, graded by [`batch::Batch`] when the
+    /// stock is selected, `None` if not resolved from a keyword.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<Confidence>,
 }
 
 impl Stock {
@@ -97,22 +1349,252 @@ impl Stock {
             name,
             code,
             exchange,
+            industry: None,
+            source: None,
+            kind: SecurityKind::Stock,
+            aliases: vec![],
+            query: None,
+            connect_eligible: None,
+            confidence: None,
         }
     }
 
     pub fn normalize(&self) -> String {
-        let val = self.code.as_str();
+        self.exchange.format(&self.code)
+    }
+
+    /// The normalized code in the `style`.
+    pub fn normalize_with(&self, style: NormalizeStyle) -> String {
+        style.format(self.exchange, &self.code)
+    }
+
+    /// The ticker of Yahoo Finance, such as `600519.SS`, see [`NormalizeStyle::Yahoo`].
+    pub fn to_yahoo(&self) -> String {
+        self.normalize_with(NormalizeStyle::Yahoo)
+    }
 
+    /// Classify the listing board from the exchange and the code prefix.
+    pub fn board(&self) -> Board {
+        if let Some(prefix) = find_prefix(self.exchange, self.kind, &self.code) {
+            return prefix.board;
+        }
         match self.exchange {
-            Exchange::ShangHai => ShangHai.format(val),
-            Exchange::ShenZhen => ShenZhen.format(val),
-            Exchange::BeiJing => BeiJing.format(val),
-            Exchange::HongKong => HongKong.format(val),
+            Exchange::ShangHai | Exchange::ShenZhen => Board::Main,
+            Exchange::BeiJing => Board::BeiJing,
+            Exchange::HongKong => Board::HongKong,
+            Exchange::UnitedStates => Board::UnitedStates,
         }
     }
+
+    /// Check the code belongs to the exchange of the stock.
+    pub fn validate(&self) -> color_eyre::Result<()> {
+        let valid = match self.kind {
+            SecurityKind::Stock => self.exchange.valid(&self.code),
+            SecurityKind::Index => Exchange::guess_from_index(&self.code)
+                .ok()
+                .filter(|v| *v == self.exchange)
+                .map(|_| ()),
+        };
+
+        valid.ok_or_else(|| {
+            color_eyre::eyre::eyre!(
+                "Stock code `{}` does not belong to exchange {:?}",
+                self.code,
+                self.exchange
+            )
+        })
+    }
+}
+
+/// Build a stock from a bare code without network access, the name is left empty.
+impl TryFrom<&str> for Stock {
+    type Error = color_eyre::Report;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let code = value.trim();
+        let exchange = Exchange::guess_from_stock(code)?;
+
+        Ok(Stock::new(String::default(), code.to_string(), exchange))
+    }
 }
 
+impl std::str::FromStr for Stock {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Stock::try_from(s)
+    }
+}
+
+/// Remove the stocks with the same normalized code, keep the first seen order.
+pub fn dedup_stocks(stocks: Vec<Stock>) -> Vec<Stock> {
+    let mut seen = std::collections::HashSet::new();
+
+    stocks
+        .into_iter()
+        .filter(|v| seen.insert(v.normalize()))
+        .collect()
+}
+
+/// The stocks have the same normalized code but different names, such as a
+/// stale name returned by one of the sources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameConflict {
+    pub normalized: String,
+    /// The different names and the sources returned them, in the first seen order.
+    pub names: Vec<(String, Option<Tool>)>,
+    /// The name the stocks are renamed to.
+    pub chosen: String,
+}
+
+/// Rename the stocks with the same normalized code to one name, the name from
+/// the `authority` source is preferred, otherwise the first seen name is kept.
+pub fn reconcile_names(stocks: &mut [Stock], authority: Tool) -> Vec<NameConflict> {
+    let mut groups: Vec<(String, Vec<usize>)> = vec![];
+    let mut index_of = std::collections::HashMap::new();
+
+    for (index, stock) in stocks.iter().enumerate() {
+        let normalized = stock.normalize();
+        let group = *index_of.entry(normalized.clone()).or_insert_with(|| {
+            groups.push((normalized, vec![]));
+            groups.len() - 1
+        });
+
+        groups[group].1.push(index);
+    }
+
+    let mut conflicts = vec![];
+
+    for (normalized, indexes) in groups {
+        let mut names: Vec<(String, Option<Tool>)> = vec![];
+
+        for index in &indexes {
+            let stock = &stocks[*index];
+
+            if !names.iter().any(|(name, _)| name == &stock.name) {
+                names.push((stock.name.clone(), stock.source));
+            }
+        }
+        if names.len() < 2 {
+            continue;
+        }
+        let chosen = indexes
+            .iter()
+            .map(|v| &stocks[*v])
+            .find(|v| v.source == Some(authority))
+            .map_or_else(|| names[0].0.clone(), |v| v.name.clone());
+
+        for index in &indexes {
+            stocks[*index].name = chosen.clone();
+        }
+        conflicts.push(NameConflict {
+            normalized,
+            names,
+            chosen,
+        });
+    }
+    conflicts
+}
+
+/// The milliseconds since the unix epoch, an error if the system clock is
+/// set before it.
+pub fn unix_millis() -> color_eyre::Result<u128> {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|v| v.as_millis())
+        .map_err(|e| color_eyre::eyre::eyre!("The system time is before the unix epoch: {e}"))
+}
+
+/// The default separators of the keywords in one line.
+pub const SPLIT_CHARS: &str = ",，、;；";
+
+/// Split the text into keywords by the lines and the `chars`, each keyword is
+/// trimmed and the empty ones are skipped. The full-width digits and letters
+/// are converted by [`half_width`].
+pub fn split_keywords(text: &str, chars: &str) -> Vec<String> {
+    text.split(|v| v == '\n' || chars.contains(v))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| half_width(v).into_owned())
+        .collect()
+}
+
+/// Convert the full-width digits and letters to the ASCII ones, such as the
+/// `６００５１９` pasted from the chinese documents. The other characters,
+/// such as the full-width punctuations, are kept.
+pub fn half_width(val: &str) -> std::borrow::Cow<'_, str> {
+    let is_full = |v: char| matches!(v, '０'..='９' | 'Ａ'..='Ｚ' | 'ａ'..='ｚ');
+
+    if !val.chars().any(is_full) {
+        return std::borrow::Cow::Borrowed(val);
+    }
+    val.chars()
+        .map(|v| {
+            if is_full(v) {
+                // the full-width forms are offset from the ASCII ones
+                char::from_u32(v as u32 - 0xFEE0).unwrap_or(v)
+            } else {
+                v
+            }
+        })
+        .collect()
+}
+
+/// Encode the keyword in GBK then percent-encode it, the sohu and hexun
+/// sites only accept the GBK keywords.
+pub fn gbk_urlencode(key: &str) -> String {
+    let (key, _, _) = encoding_rs::GBK.encode(key);
+
+    urlencoding::encode_binary(&key).into_owned()
+}
+
+/// Check the code is 6 digits for mainland or 5 digits for HK, the mainland
+/// codes truncated to 5 digits are rejected instead of taken as HK.
+pub fn validate_code_shape(code: &str) -> color_eyre::Result<()> {
+    if code.is_empty() || !code.bytes().all(|v| v.is_ascii_digit()) {
+        return Err(color_eyre::eyre::eyre!(
+            "Stock code `{code}` should only contain digits"
+        ));
+    }
+    match code.len() {
+        6 => Ok(()),
+        5 if matches!(&code[0..2], "60" | "68" | "30") => Err(color_eyre::eyre::eyre!(
+            "Stock code `{code}` looks like a truncated mainland code, expect 6 digits"
+        )),
+        5 => Ok(()),
+        len => Err(color_eyre::eyre::eyre!(
+            "Stock code `{code}` has {len} digits, expect 6 for mainland or 5 for HK"
+        )),
+    }
+}
+
+/// Zero-pad a 1 to 5 digits HK code to 5 digits, such as `700` to `00700`.
+/// The codes longer than 5 digits are never padded.
+pub fn pad_hk_code(code: &str) -> Option<String> {
+    let code = code.trim();
+
+    (!code.is_empty() && code.len() <= 5 && code.bytes().all(|v| v.is_ascii_digit()))
+        .then(|| format!("{code:0>5}"))
+}
+
+/// Normalize the code in the known exchange context, the short HK codes are
+/// zero-padded first. Without a context it is same as [`normalize_stock_number`].
+pub fn normalize_stock_number_in(val: &str, exchange: Option<Exchange>) -> Option<String> {
+    let val = &half_width(val);
+
+    match exchange {
+        Some(Exchange::HongKong) => pad_hk_code(val).map(|v| HongKong.format(&v)),
+        Some(exchange) => exchange.valid(val).map(|_| exchange.format(val)),
+        None => normalize_stock_number(val),
+    }
+}
+
+/// Normalize the code by guessing the exchange, the full-width digits are
+/// converted first.
 pub fn normalize_stock_number(val: &str) -> Option<String> {
+    let val = &half_width(val);
+
+    validate_code_shape(val).ok()?;
     if HongKong.valid(val).is_some() {
         Some(HongKong.format(val))
     } else if ShangHai.valid(val).is_some() {
@@ -145,13 +1627,7 @@ impl Format for ShangHai {
 
 impl Valid for ShangHai {
     fn valid(&self, val: &str) -> Option<()> {
-        if let Some(v) = val.get(0..2)
-            && matches!(v, "68" | "60")
-        {
-            return Some(());
-        }
-
-        None
+        find_prefix(Exchange::ShangHai, SecurityKind::Stock, val).map(|_| ())
     }
 }
 
@@ -166,13 +1642,7 @@ impl Format for ShenZhen {
 
 impl Valid for ShenZhen {
     fn valid(&self, val: &str) -> Option<()> {
-        if let Some(v) = val.get(0..2)
-            && matches!(v, "00" | "30")
-        {
-            return Some(());
-        }
-
-        None
+        find_prefix(Exchange::ShenZhen, SecurityKind::Stock, val).map(|_| ())
     }
 }
 
@@ -187,13 +1657,7 @@ impl Format for BeiJing {
 
 impl Valid for BeiJing {
     fn valid(&self, val: &str) -> Option<()> {
-        if let Some(v) = val.get(0..2)
-            && matches!(v, "88" | "87" | "83" | "43")
-        {
-            return Some(());
-        }
-
-        None
+        find_prefix(Exchange::BeiJing, SecurityKind::Stock, val).map(|_| ())
     }
 }
 
@@ -202,6 +1666,8 @@ pub struct HongKong;
 
 impl Format for HongKong {
     fn format(&self, val: &str) -> String {
+        let val = pad_hk_code(val).unwrap_or_else(|| val.to_string());
+
         format!("{}{}", 5, val)
     }
 }
@@ -211,3 +1677,22 @@ impl Valid for HongKong {
         (val.len() == 5).then_some(())
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnitedStates;
+
+impl Format for UnitedStates {
+    fn format(&self, val: &str) -> String {
+        format!("{}{}", 74, val.to_ascii_uppercase())
+    }
+}
+
+impl Valid for UnitedStates {
+    fn valid(&self, val: &str) -> Option<()> {
+        (!val.is_empty()
+            && val.len() <= 6
+            && val.starts_with(|v: char| v.is_ascii_alphabetic())
+            && val.chars().all(|v| v.is_ascii_alphanumeric() || v == '.'))
+        .then_some(())
+    }
+}
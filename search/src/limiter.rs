@@ -0,0 +1,73 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A token bucket limits the rate of the requests, the clones share the
+/// bucket, so the rate is bounded across all the sources holding a clone.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// The requests per second
+    rps: f64,
+
+    /// The most tokens saved while idle, the size of a burst
+    burst: f64,
+
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    /// Allow `rps` requests per second, the burst is one request.
+    pub fn new(rps: f64) -> color_eyre::Result<Self> {
+        if !rps.is_finite() || rps <= 0.0 {
+            return Err(color_eyre::eyre::eyre!(
+                "Not a valid rate `{rps}`, expect a positive number"
+            ));
+        }
+        Ok(Self {
+            rps,
+            burst: 1.0,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: 1.0,
+                last: Instant::now(),
+            })),
+        })
+    }
+
+    /// Allow at most `burst` requests at once after an idle time.
+    pub fn with_burst(mut self, burst: usize) -> Self {
+        self.burst = burst.max(1) as f64;
+        self
+    }
+
+    pub fn rps(&self) -> f64 {
+        self.rps
+    }
+
+    /// Wait until a request is allowed.
+    pub async fn acquire(&self) {
+        while let Some(wait) = self.try_acquire() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Take a token, or return the time until the next one.
+    fn try_acquire(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last).as_secs_f64();
+
+        bucket.tokens = (bucket.tokens + elapsed * self.rps).min(self.burst);
+        bucket.last = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rps))
+        }
+    }
+}
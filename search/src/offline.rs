@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::Stock;
+
+/// The bundled securities, see [`Offline::bundled`].
+#[cfg(feature = "offline")]
+pub const BUNDLED: &str = include_str!("offline.csv");
+
+/// The local `name -> stock` table consulted before the network, the names
+/// in it are resolved without searching.
+#[derive(Debug, Clone, Default)]
+pub struct Offline {
+    map: HashMap<String, Stock>,
+}
+
+impl Offline {
+    /// The table of the [`BUNDLED`] securities, the most common ones only.
+    #[cfg(feature = "offline")]
+    pub fn bundled() -> color_eyre::Result<Self> {
+        Self::parse_csv(BUNDLED)
+    }
+
+    pub fn load(path: &Path) -> color_eyre::Result<Self> {
+        Self::parse_csv(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse the lines of `name,code`, such as `贵州茅台,600519.SH`, the code
+    /// is parsed by [`crate::block::parse_code`]. The empty lines and the
+    /// lines start with `#` are skipped.
+    pub fn parse_csv(text: &str) -> color_eyre::Result<Self> {
+        let mut offline = Self::default();
+
+        for (index, line) in text.lines().map(str::trim).enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, code) = line.split_once([',', '，']).ok_or_else(|| {
+                color_eyre::eyre::eyre!("Not a valid offline line `{line}` at line {}", index + 1)
+            })?;
+            let mut stock = crate::block::parse_code(code)
+                .map_err(|e| color_eyre::eyre::eyre!("{e} at line {}", index + 1))?;
+
+            stock.name = name.trim().to_string();
+            offline.insert(stock);
+        }
+        Ok(offline)
+    }
+
+    pub fn insert(&mut self, stock: Stock) {
+        self.map.insert(stock.name.clone(), stock);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// The stock whose name is exactly the keyword.
+    pub fn get(&self, keyword: &str) -> Option<&Stock> {
+        self.map.get(keyword.trim())
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{Exchange, Format, Stock};
+
+/// The user supplied `code -> exchange` map, it takes precedence over the
+/// built-in prefix rules of [`Exchange::guess_from_stock`].
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    map: HashMap<String, Exchange>,
+}
+
+impl Overrides {
+    /// Load the map from the file, a `.json` file is an object of code to
+    /// exchange, others are csv lines of `code,exchange`.
+    pub fn load(path: &Path) -> color_eyre::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+
+        if path
+            .extension()
+            .is_some_and(|v| v.eq_ignore_ascii_case("json"))
+        {
+            Self::parse_json(&text)
+        } else {
+            Self::parse_csv(&text)
+        }
+    }
+
+    /// Parse the csv lines of `code,exchange`, the empty lines and the lines
+    /// start with `#` are skipped.
+    pub fn parse_csv(text: &str) -> color_eyre::Result<Self> {
+        let mut overrides = Self::default();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (code, exchange) = line
+                .split_once(',')
+                .ok_or_else(|| color_eyre::eyre::eyre!("Not a valid override line `{line}`"))?;
+
+            overrides.insert(code.trim(), exchange.trim().parse()?);
+        }
+        Ok(overrides)
+    }
+
+    /// Parse the json object of code to exchange, such as `{"900901": "sh"}`.
+    pub fn parse_json(text: &str) -> color_eyre::Result<Self> {
+        let map: HashMap<String, String> = serde_json::from_str(text)?;
+        let mut overrides = Self::default();
+
+        for (code, exchange) in map {
+            overrides.insert(code.trim(), exchange.parse()?);
+        }
+        Ok(overrides)
+    }
+
+    pub fn insert(&mut self, code: &str, exchange: Exchange) {
+        self.map.insert(code.to_string(), exchange);
+    }
+
+    pub fn get(&self, code: &str) -> Option<Exchange> {
+        self.map.get(code.trim()).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Guess the exchange of the code, the override goes first.
+    pub fn guess(&self, code: &str) -> color_eyre::Result<Exchange> {
+        match self.get(code) {
+            Some(exchange) => Ok(exchange),
+            None => Exchange::guess_from_stock(code),
+        }
+    }
+
+    /// Same as [`crate::normalize_stock_number`], the override goes first.
+    pub fn normalize(&self, code: &str) -> Option<String> {
+        match self.get(code) {
+            Some(exchange) => Some(exchange.format(code.trim())),
+            None => crate::normalize_stock_number(code),
+        }
+    }
+
+    /// Build a stock from a bare code, same as `Stock::try_from` but the
+    /// override goes first.
+    pub fn stock(&self, code: &str) -> color_eyre::Result<Stock> {
+        let code = code.trim();
+
+        Ok(Stock::new(
+            String::default(),
+            code.to_string(),
+            self.guess(code)?,
+        ))
+    }
+
+    /// Apply the override to the stock converted from the source.
+    pub fn apply(&self, stock: &mut Stock) {
+        if let Some(exchange) = self.get(&stock.code) {
+            stock.exchange = exchange;
+        }
+    }
+}
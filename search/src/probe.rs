@@ -0,0 +1,127 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::eyre::eyre;
+use reqwest::{Client, StatusCode};
+
+/// How the source checks the reachability when initialized.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Probe {
+    /// Run the healthcheck of the source against its homepage
+    #[default]
+    Homepage,
+    /// Probe the url instead, such as the api host when the homepage is blocked
+    Url(String),
+    /// Skip the check
+    Skip,
+}
+
+impl Probe {
+    pub async fn check<T: crate::Search>(
+        &self,
+        tool: &T,
+        client: &Client,
+        homepage: &str,
+        name: &str,
+    ) -> color_eyre::Result<()> {
+        match self {
+            Probe::Homepage => cached(homepage, tool.healthcheck()).await,
+            Probe::Url(url) => probe(client, url, name).await,
+            Probe::Skip => Ok(()),
+        }
+    }
+}
+
+/// How long a successful probe of a host is trusted.
+pub const PROBE_TTL: Duration = Duration::from_secs(60);
+
+/// Check the `url` is reachable, skip the request if the host was probed
+/// successfully within [`PROBE_TTL`].
+pub async fn probe(client: &Client, url: &str, name: &str) -> color_eyre::Result<()> {
+    cached(url, check(client, url, name)).await
+}
+
+/// Run the `check` of the `url` unless the host passed it within [`PROBE_TTL`].
+pub async fn cached(
+    url: &str,
+    check: impl Future<Output = color_eyre::Result<()>>,
+) -> color_eyre::Result<()> {
+    let cache = cache_path(url);
+
+    if let Some(cache) = &cache
+        && is_fresh(cache)
+    {
+        return Ok(());
+    }
+
+    check.await?;
+    if let Some(cache) = &cache {
+        // a failed write only means the next run probes again
+        let _ = touch(cache);
+    }
+
+    Ok(())
+}
+
+/// Check the `url` is reachable, always send the request.
+pub async fn check(client: &Client, url: &str, name: &str) -> color_eyre::Result<()> {
+    // HEAD is enough for reachability, but some sites refuse it
+    let mut res = client.head(url).send().await?;
+
+    if res.status() == StatusCode::METHOD_NOT_ALLOWED {
+        res = client.get(url).send().await?;
+    }
+
+    if !res.status().is_success() {
+        return Err(eyre!("Can not access {name} website: {}", res.status()));
+    }
+
+    Ok(())
+}
+
+/// Visit the `url` with a full GET so the session cookies are stored in the client.
+pub async fn warm_up(client: &Client, url: &str, name: &str) -> color_eyre::Result<()> {
+    let res = client.get(url).send().await?;
+
+    if !res.status().is_success() {
+        return Err(eyre!("Can not warm up {name} website: {}", res.status()));
+    }
+    // read the body so the connection can be reused
+    res.bytes().await?;
+
+    Ok(())
+}
+
+fn cache_path(url: &str) -> Option<PathBuf> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let host = url.host_str()?;
+
+    Some(std::env::temp_dir().join("stockconverter").join(host))
+}
+
+fn now() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|v| v.as_secs())
+}
+
+fn is_fresh(cache: &Path) -> bool {
+    let last = std::fs::read_to_string(cache)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok());
+
+    match (last, now()) {
+        (Some(last), Some(now)) => now.saturating_sub(last) < PROBE_TTL.as_secs(),
+        _ => false,
+    }
+}
+
+fn touch(cache: &Path) -> std::io::Result<()> {
+    if let Some(parent) = cache.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(cache, now().unwrap_or_default().to_string())
+}
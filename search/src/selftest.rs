@@ -0,0 +1,77 @@
+use crate::all::Source;
+use crate::{Exchange, MatchPreference, SearchOptions, Tool};
+
+/// A keyword and the code it is expected to resolve to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub keyword: &'static str,
+    pub code: &'static str,
+    pub exchange: Exchange,
+}
+
+/// The built-in samples, the well known stocks unlikely to be renamed.
+pub const SAMPLES: [Sample; 2] = [
+    Sample {
+        keyword: "贵州茅台",
+        code: "600519",
+        exchange: Exchange::ShangHai,
+    },
+    Sample {
+        keyword: "腾讯控股",
+        code: "00700",
+        exchange: Exchange::HongKong,
+    },
+];
+
+/// The samples of the tool, the HK samples are skipped for cfi, it only
+/// returns the mainland codes.
+pub fn samples_of(tool: Tool) -> Vec<Sample> {
+    let hk = tool != Tool::Cfi;
+
+    SAMPLES
+        .into_iter()
+        .filter(|v| hk || v.exchange != Exchange::HongKong)
+        .collect()
+}
+
+/// The result of a sample, `actual` is the code resolved by the source.
+#[derive(Debug)]
+pub struct Outcome {
+    pub tool: Tool,
+    pub sample: Sample,
+    pub actual: color_eyre::Result<String>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.actual.as_deref().is_ok_and(|v| v == self.sample.code)
+    }
+}
+
+/// Resolve the samples of the source one by one.
+pub async fn run(source: &Source) -> Vec<Outcome> {
+    let tool = source.tool();
+    let mut outcomes = vec![];
+
+    for sample in samples_of(tool) {
+        let hk = sample.exchange == Exchange::HongKong;
+        let options = SearchOptions::default().with_prefer(if hk {
+            MatchPreference::HShareFirst
+        } else {
+            MatchPreference::AShareFirst
+        });
+        let actual = match source.search_stocks(sample.keyword, hk).await {
+            Ok(stocks) => options
+                .select_or_err(options.rank(sample.keyword, stocks))
+                .map(|v| v.code),
+            Err(e) => Err(e),
+        };
+
+        outcomes.push(Outcome {
+            tool,
+            sample,
+            actual,
+        });
+    }
+    outcomes
+}
@@ -1,26 +1,63 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::PathBuf;
 
-use color_eyre::eyre::eyre;
 use neure::{neu::whitespace, prelude::*};
 use reqwest::Client;
 
 use crate::{Exchange, Stock};
 
-#[derive(Debug)]
+pub const HOMEPAGE: &str = "https://finance.sina.com.cn";
+
+/// The scheme and host of the api.
+pub const BASE_URL: &str = "https://suggest3.sinajs.cn";
+
+/// The source is `Send + Sync`, cloning it only clones the reference counted
+/// client, the clones share the connection pool and the cookies.
+#[derive(Debug, Clone)]
 pub struct Sina {
     pub reqwest: Client,
+
+    /// The scheme and host of the api, default is [`BASE_URL`]
+    pub base_url: String,
+
+    /// Write the response bodies to the directory, see [`crate::dump_response`]
+    pub dump_dir: Option<PathBuf>,
 }
 
 impl Sina {
     pub async fn init(builder: reqwest::ClientBuilder) -> color_eyre::Result<Self> {
-        let reqwest = builder.build()?;
-        let res = reqwest.get("https://finance.sina.com.cn").send().await?;
+        Self::init_with_client(crate::decompression(builder).build()?).await
+    }
+
+    /// Initialize with a shared client, its connection pool and cookie store are reused.
+    pub async fn init_with_client(reqwest: Client) -> color_eyre::Result<Self> {
+        Self::init_with_probe(reqwest, &crate::probe::Probe::default()).await
+    }
+
+    /// Initialize with a shared client, check the reachability with the `probe`.
+    pub async fn init_with_probe(
+        reqwest: Client,
+        probe: &crate::probe::Probe,
+    ) -> color_eyre::Result<Self> {
+        let tool = Self {
+            reqwest,
+            base_url: BASE_URL.to_string(),
+            dump_dir: None,
+        };
 
-        if !res.status().is_success() {
-            return Err(eyre!("Can not access sina website: {}", res.status()));
-        }
+        probe.check(&tool, &tool.reqwest, HOMEPAGE, "sina").await?;
 
-        Ok(Self { reqwest })
+        Ok(tool)
+    }
+
+    /// Send the requests to the `base_url` instead, such as a mirror or a mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_dump_dir(mut self, dump_dir: Option<PathBuf>) -> Self {
+        self.dump_dir = dump_dir;
+        self
     }
 }
 
@@ -32,14 +69,39 @@ pub struct Input {
 }
 
 impl Default for Input {
+    /// Same as [`Input::try_default`], the parameter is zero if the system
+    /// clock is broken, it only needs to change between the requests.
     fn default() -> Self {
-        let time = SystemTime::now();
-        let elpased = time.duration_since(UNIX_EPOCH).expect("Ooop!");
+        Self::try_default().unwrap_or_else(|_| Self {
+            key: Default::default(),
+            count: 0,
+        })
+    }
+}
 
-        Self {
+impl Input {
+    /// The input with the current unix time, an error if the system clock is
+    /// set before the unix epoch.
+    pub fn try_default() -> color_eyre::Result<Self> {
+        Ok(Self {
             key: Default::default(),
-            count: elpased.as_millis() as usize,
-        }
+            count: crate::unix_millis()? as usize,
+        })
+    }
+
+    pub fn new(keyword: impl Into<String>) -> Self {
+        Self::default().with_keyword(keyword)
+    }
+
+    pub fn with_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.key = keyword.into();
+        self
+    }
+
+    /// Set the callback suffix, defaults to the unix time in milliseconds
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
     }
 }
 
@@ -48,6 +110,10 @@ impl crate::QueryInput for Input {
         self.key = keyword;
     }
 
+    fn keyword(&self) -> &str {
+        &self.key
+    }
+
     fn reset(&mut self) {
         self.count += 1;
     }
@@ -57,6 +123,8 @@ impl crate::QueryInput for Input {
 pub struct Output {
     code: String,
     name: String,
+    /// The other names of the suggestion, such as the full name
+    aliases: Vec<String>,
 }
 
 impl TryFrom<Output> for Stock {
@@ -65,11 +133,10 @@ impl TryFrom<Output> for Stock {
     fn try_from(value: Output) -> Result<Self, Self::Error> {
         let exchange = Exchange::guess_from_stock(&value.code);
 
-        Ok(Stock {
-            name: value.name,
-            code: value.code,
-            exchange: exchange?,
-        })
+        let mut stock = Stock::new(value.name, value.code, exchange?);
+
+        stock.aliases = value.aliases;
+        Ok(stock)
     }
 }
 
@@ -77,34 +144,71 @@ impl crate::Search for Sina {
     type Input = Input;
     type Output = Output;
 
+    async fn healthcheck(&self) -> color_eyre::Result<()> {
+        crate::probe::check(&self.reqwest, HOMEPAGE, "sina").await
+    }
+
     async fn search_all(&self, info: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
         let url = format!(
-            "https://suggest3.sinajs.cn/suggest/type=&key={}&name=suggestdata_{}",
-            info.key, info.count
+            "{}/suggest/type=&key={}&name=suggestdata_{}",
+            self.base_url, info.key, info.count
         );
 
         let res = self.reqwest.get(url).send().await?;
-        let text = res.text().await?;
-
-        let name = '='.not().repeat_full();
-        let fields = re::array([';', ',', '"']).not().repeat_full().sep(",");
-        let parser = "var"
-            .sep_once(
-                whitespace().repeat_full(),
-                name.sep_once("=", fields.sep(";").quote("\"", "\"")),
-            )
-            .map(|(_, (_, fields))| Ok(fields));
-        let suggests = CharsCtx::new(&text)
-            .ignore(whitespace().repeat_full())
-            .ctor(&parser)?;
-
-        Ok(suggests
-            .into_iter()
-            .filter(|v| v.len() >= 3)
-            .map(|v| Output {
+        let text = crate::response_text(res, "sina").await?;
+
+        crate::dump_response(self.dump_dir.as_deref(), "sina", &info.key, &text)?;
+
+        parse(&text)
+    }
+}
+
+/// Parse the javascript assignment returned by sina.
+pub fn parse(text: &str) -> color_eyre::Result<Vec<Output>> {
+    // sina returns an empty string when nothing matches
+    if let Some((_, body)) = text.split_once('=')
+        && matches!(body.trim().trim_end_matches(';').trim_end(), "\"\"" | "")
+    {
+        return Ok(vec![]);
+    }
+
+    let head = "var"
+        .sep_once(whitespace().repeat_full(), '='.not().repeat_full())
+        .map(|(_, name)| Ok(name));
+    let rows = '"'.not().repeat_full().quote("=\"", "\"");
+    let mut ctx = CharsCtx::new(text);
+
+    // the parser starts after the leading whitespace
+    ctx.inc(text.len() - text.trim_start().len());
+
+    let _: &str = ctx
+        .ctor(&head)
+        .map_err(|e| crate::parse_error("sina", text, ctx.offset(), e))?;
+    let rows: &str = ctx
+        .ctor(&rows)
+        .map_err(|e| crate::parse_error("sina", text, ctx.offset(), e))?;
+
+    // skip the empty rows, such as the one after a trailing `;`, and the
+    // malformed rows, the other rows are still usable
+    Ok(rows
+        .split(';')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| v.split(',').collect::<Vec<_>>())
+        .filter(|v| v.len() >= 3)
+        .map(|v| {
+            let mut aliases: Vec<String> = vec![];
+
+            for alias in [v.get(4), v.get(6)].into_iter().flatten() {
+                if !alias.is_empty() && *alias != v[0] && !aliases.iter().any(|a| a == *alias) {
+                    aliases.push(alias.to_string());
+                }
+            }
+            Output {
                 code: v[2].to_string(),
                 name: v[0].to_string(),
-            })
-            .collect())
-    }
+                aliases,
+            }
+        })
+        .collect())
 }
@@ -1,28 +1,63 @@
-use std::{
-    str::FromStr,
-    time::{SystemTime, UNIX_EPOCH},
-};
+use std::{path::PathBuf, str::FromStr};
 
 use color_eyre::eyre::eyre;
 use reqwest::Client;
 
-use crate::{Exchange, Stock};
+use crate::{Exchange, SecurityKind, Stock};
 
-#[derive(Debug)]
+pub const HOMEPAGE: &str = "https://q.stock.sohu.com";
+
+/// The scheme and host of the api.
+pub const BASE_URL: &str = "https://q.stock.sohu.com";
+
+/// The source is `Send + Sync`, cloning it only clones the reference counted
+/// client, the clones share the connection pool and the cookies.
+#[derive(Debug, Clone)]
 pub struct SoHu {
     pub reqwest: Client,
+
+    /// The scheme and host of the api, default is [`BASE_URL`]
+    pub base_url: String,
+
+    /// Write the response bodies to the directory, see [`crate::dump_response`]
+    pub dump_dir: Option<PathBuf>,
 }
 
 impl SoHu {
     pub async fn init(builder: reqwest::ClientBuilder) -> color_eyre::Result<Self> {
-        let reqwest = builder.build()?;
-        let res = reqwest.get("https://q.stock.sohu.com").send().await?;
+        Self::init_with_client(crate::decompression(builder).build()?).await
+    }
 
-        if !res.status().is_success() {
-            return Err(eyre!("Can not access sohu website: {}", res.status()));
-        }
+    /// Initialize with a shared client, its connection pool and cookie store are reused.
+    pub async fn init_with_client(reqwest: Client) -> color_eyre::Result<Self> {
+        Self::init_with_probe(reqwest, &crate::probe::Probe::default()).await
+    }
+
+    /// Initialize with a shared client, check the reachability with the `probe`.
+    pub async fn init_with_probe(
+        reqwest: Client,
+        probe: &crate::probe::Probe,
+    ) -> color_eyre::Result<Self> {
+        let tool = Self {
+            reqwest,
+            base_url: BASE_URL.to_string(),
+            dump_dir: None,
+        };
+
+        probe.check(&tool, &tool.reqwest, HOMEPAGE, "sohu").await?;
+
+        Ok(tool)
+    }
+
+    /// Send the requests to the `base_url` instead, such as a mirror or a mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
 
-        Ok(Self { reqwest })
+    pub fn with_dump_dir(mut self, dump_dir: Option<PathBuf>) -> Self {
+        self.dump_dir = dump_dir;
+        self
     }
 }
 
@@ -34,14 +69,39 @@ pub struct Input {
 }
 
 impl Default for Input {
+    /// Same as [`Input::try_default`], the parameter is zero if the system
+    /// clock is broken, it only needs to change between the requests.
     fn default() -> Self {
-        let time = SystemTime::now();
-        let elpased = time.duration_since(UNIX_EPOCH).expect("Ooop!");
+        Self::try_default().unwrap_or_else(|_| Self {
+            key: Default::default(),
+            time: 0,
+        })
+    }
+}
 
-        Self {
+impl Input {
+    /// The input with the current unix time, an error if the system clock is
+    /// set before the unix epoch.
+    pub fn try_default() -> color_eyre::Result<Self> {
+        Ok(Self {
             key: Default::default(),
-            time: elpased.as_millis() as usize,
-        }
+            time: crate::unix_millis()? as usize,
+        })
+    }
+
+    pub fn new(keyword: impl Into<String>) -> Self {
+        Self::default().with_keyword(keyword)
+    }
+
+    pub fn with_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.key = keyword.into();
+        self
+    }
+
+    /// Set the cache busting parameter, defaults to the unix time in milliseconds
+    pub fn with_time(mut self, time: usize) -> Self {
+        self.time = time;
+        self
     }
 }
 
@@ -49,25 +109,38 @@ impl crate::QueryInput for Input {
     fn set_keyword(&mut self, keyword: String) {
         self.key = keyword;
     }
+
+    fn keyword(&self) -> &str {
+        &self.key
+    }
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct Output {
     code: String,
     name: String,
+    /// The market of sohu, `cn`, `hk` or [`MARKET_INDEX`]
+    #[serde(default)]
+    market: String,
 }
 
+/// The market of the index results.
+pub const MARKET_INDEX: &str = "zs";
+
 impl TryFrom<Output> for Stock {
     type Error = color_eyre::Report;
 
     fn try_from(value: Output) -> Result<Self, Self::Error> {
+        if value.market == MARKET_INDEX {
+            let exchange = Exchange::guess_from_index(&value.code)?;
+            let mut stock = Stock::new(value.name, value.code, exchange);
+
+            stock.kind = SecurityKind::Index;
+            return Ok(stock);
+        }
         let exchange = Exchange::guess_from_stock(&value.code);
 
-        Ok(Stock {
-            name: value.name,
-            code: value.code,
-            exchange: exchange?,
-        })
+        Ok(Stock::new(value.name, value.code, exchange?))
     }
 }
 
@@ -75,46 +148,78 @@ impl crate::Search for SoHu {
     type Input = Input;
     type Output = Output;
 
-    async fn search_all(&self, info: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
-        use neure::prelude::*;
+    async fn healthcheck(&self) -> color_eyre::Result<()> {
+        crate::probe::check(&self.reqwest, HOMEPAGE, "sohu").await
+    }
 
-        let (key, _, _) = encoding_rs::GBK.encode(&info.key);
-        let key = urlencoding::encode_binary(&key);
+    async fn search_all(&self, info: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
+        let key = crate::gbk_urlencode(&info.key);
         let method = "search";
-        let callback = "searchBox1.output";
+        let callback = CALLBACK;
         let ty = "all";
         let url = format!(
-            "https://q.stock.sohu.com/app1/stockSearch?method={}&callback={}&type={}&keyword={}&_={}",
-            method, callback, ty, key, info.time
+            "{}/app1/stockSearch?method={}&callback={}&type={}&keyword={}&_={}",
+            self.base_url, method, callback, ty, key, info.time
         );
         let res = self.reqwest.get(url).send().await?;
-        let text = res.text().await?;
-
-        let item: neu::Not<[char; 2], char> = re::array(['(', ')']).not();
-        let parser = item.repeat_full().then(item.repeat_full().quote("(", ")"));
-        let json = CharsCtx::new(&text).ctor(&parser).map(|(_, a)| a)?;
-        let json = serde_json::Value::from_str(json)?;
-        let array = json
-            .get("result")
-            .and_then(|v| v.as_array())
-            .ok_or_else(|| eyre!("Invalid json format"))?;
-
-        let mut outputs = vec![];
-
-        for item in array {
-            if let Some(array) = item.as_array().filter(|v| v.len() >= 3)
-                && let (Some(code), Some(name)) = (
-                    array.get(1).and_then(|v| v.as_str()),
-                    array.get(2).and_then(|v| v.as_str()),
-                )
-            {
-                let code = code.to_string();
-                let name = name.chars().filter(|v| !v.is_ascii()).collect::<String>();
-
-                outputs.push(Output { code, name });
-            }
-        }
+        let text = crate::response_text(res, "sohu").await?;
 
-        Ok(outputs)
+        crate::dump_response(self.dump_dir.as_deref(), "sohu", &info.key, &text)?;
+
+        parse(&text)
     }
 }
+
+/// The jsonp callback name requested from sohu.
+pub const CALLBACK: &str = "searchBox1.output";
+
+/// Return the arguments of the jsonp callback, the outermost parentheses after
+/// the [`CALLBACK`] name are matched, so the parentheses inside of the names
+/// such as `(ADR)` are kept.
+pub fn unwrap_callback(text: &str) -> color_eyre::Result<&str> {
+    let start = match text.find(CALLBACK) {
+        Some(offset) => text[offset + CALLBACK.len()..]
+            .find('(')
+            .map(|v| offset + CALLBACK.len() + v),
+        None => text.find('('),
+    };
+    let start = start.ok_or_else(|| eyre!("Not a valid return from sohu: {text}"))?;
+    let end = text
+        .rfind(')')
+        .filter(|v| *v > start)
+        .ok_or_else(|| eyre!("Not a valid return from sohu: {text}"))?;
+
+    Ok(&text[start + 1..end])
+}
+
+/// Parse the jsonp callback returned by sohu.
+pub fn parse(text: &str) -> color_eyre::Result<Vec<Output>> {
+    let json = serde_json::Value::from_str(unwrap_callback(text)?)?;
+    let array = json
+        .get("result")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| eyre!("Invalid json format"))?;
+
+    let mut outputs = vec![];
+
+    for item in array {
+        if let Some(array) = item.as_array().filter(|v| v.len() >= 3)
+            && let (Some(code), Some(name)) = (
+                array.get(1).and_then(|v| v.as_str()),
+                array.get(2).and_then(|v| v.as_str()),
+            )
+        {
+            let code = code.to_string();
+            let name = name.chars().filter(|v| !v.is_ascii()).collect::<String>();
+            let market = array
+                .first()
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+
+            outputs.push(Output { code, name, market });
+        }
+    }
+
+    Ok(outputs)
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The bundled abbreviations, see [`Synonyms::bundled`].
+pub const BUNDLED: &str = include_str!("synonyms.csv");
+
+/// The local `abbreviation -> full name` table, the keywords are expanded
+/// before searching because some sources only match the full names.
+#[derive(Debug, Clone, Default)]
+pub struct Synonyms {
+    map: HashMap<String, String>,
+}
+
+impl Synonyms {
+    /// The table of the [`BUNDLED`] abbreviations.
+    pub fn bundled() -> color_eyre::Result<Self> {
+        Self::parse_csv(BUNDLED)
+    }
+
+    /// Load the csv file over the bundled abbreviations, the entries of the
+    /// file take precedence.
+    pub fn load(path: &Path) -> color_eyre::Result<Self> {
+        let mut synonyms = Self::bundled()?;
+
+        synonyms.extend(Self::parse_csv(&std::fs::read_to_string(path)?)?);
+        Ok(synonyms)
+    }
+
+    /// Parse the lines of `abbreviation,full name`, such as `茅台,贵州茅台`,
+    /// the empty lines and the lines start with `#` are skipped.
+    pub fn parse_csv(text: &str) -> color_eyre::Result<Self> {
+        let mut synonyms = Self::default();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (from, to) = line
+                .split_once([',', '，'])
+                .ok_or_else(|| color_eyre::eyre::eyre!("Not a valid synonym line `{line}`"))?;
+
+            synonyms.insert(from.trim(), to.trim());
+        }
+        Ok(synonyms)
+    }
+
+    pub fn insert(&mut self, from: impl Into<String>, to: impl Into<String>) {
+        self.map.insert(from.into(), to.into());
+    }
+
+    pub fn extend(&mut self, other: Synonyms) {
+        self.map.extend(other.map);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// The full name of the keyword, or the keyword itself if it is not an abbreviation.
+    pub fn expand<'a>(&'a self, keyword: &'a str) -> &'a str {
+        self.map.get(keyword.trim()).map_or(keyword, String::as_str)
+    }
+}
@@ -0,0 +1,331 @@
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use search::batch::{Batch, BatchReport, Progress, RetryConfig};
+use search::{Confidence, Exchange, QueryInput, Search, Stock};
+
+#[derive(Debug, Clone, Default)]
+struct Input(String);
+
+impl QueryInput for Input {
+    fn set_keyword(&mut self, keyword: String) {
+        self.0 = keyword;
+    }
+
+    fn keyword(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A source returns the A share and the H share of 招商银行.
+struct DualListed;
+
+impl Search for DualListed {
+    type Input = Input;
+    type Output = Stock;
+
+    async fn search_all(&self, input: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
+        Ok(match input.0.as_str() {
+            "招商银行" => vec![
+                Stock::new("招商银行".into(), "600036".into(), Exchange::ShangHai),
+                Stock::new("招商银行".into(), "03968".into(), Exchange::HongKong),
+            ],
+            _ => vec![],
+        })
+    }
+}
+
+#[tokio::test]
+async fn run_all_keeps_every_candidate() {
+    let keywords = vec!["招商银行".to_string(), "不存在".to_string()];
+    let mut resolved = 0;
+    let mut reporter = |progress: Progress<'_>| {
+        if let Progress::Resolved { .. } = progress {
+            resolved += 1;
+        }
+        ControlFlow::Continue(())
+    };
+    let results = Batch::default()
+        .with_keep_query(true)
+        .run_all(
+            &DualListed,
+            Input::default(),
+            keywords.clone(),
+            &mut reporter,
+        )
+        .await;
+
+    assert_eq!(resolved, 2);
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].1.len(), 2);
+    assert!(
+        results[0]
+            .1
+            .iter()
+            .all(|v| v.query.as_deref() == Some("招商银行"))
+    );
+    assert!(results[1].1.is_empty());
+
+    let first = Batch::default()
+        .run(
+            &DualListed,
+            Input::default(),
+            keywords,
+            &mut |_: Progress<'_>| ControlFlow::Continue(()),
+        )
+        .await;
+
+    assert_eq!(first[0].1.as_ref().map(|v| v.code.as_str()), Some("600036"));
+    assert!(first[1].1.is_none());
+}
+
+/// A source fails the first search, then finds 贵州茅台.
+#[derive(Default)]
+struct Flaky(AtomicUsize);
+
+impl Search for Flaky {
+    type Input = Input;
+    type Output = Stock;
+
+    async fn search_all(&self, _: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
+        if self.0.fetch_add(1, Ordering::SeqCst) == 0 {
+            return Err(color_eyre::eyre::eyre!("connection reset"));
+        }
+        Ok(vec![Stock::new(
+            "贵州茅台".into(),
+            "600519".into(),
+            Exchange::ShangHai,
+        )])
+    }
+}
+
+/// A source finds nothing, count the searches.
+#[derive(Default)]
+struct Empty(AtomicUsize);
+
+impl Search for Empty {
+    type Input = Input;
+    type Output = Stock;
+
+    async fn search_all(&self, _: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(vec![])
+    }
+}
+
+async fn run_with_retry<T: Search<Input = Input>>(tool: &T, retry: RetryConfig) -> Option<Stock> {
+    Batch::default()
+        .with_retry(retry)
+        .run(
+            tool,
+            Input::default(),
+            vec!["茅台".to_string()],
+            &mut |_: Progress<'_>| ControlFlow::Continue(()),
+        )
+        .await
+        .remove(0)
+        .1
+}
+
+#[tokio::test]
+async fn retry_on_error() {
+    let only_empty = RetryConfig::default().with_retry_on_empty(true);
+    let flaky = Flaky::default();
+
+    assert!(run_with_retry(&flaky, only_empty).await.is_none());
+    assert_eq!(flaky.0.load(Ordering::SeqCst), 1);
+
+    let only_error = RetryConfig::default().with_retry_on_error(true);
+    let flaky = Flaky::default();
+    let stock = run_with_retry(&flaky, only_error).await;
+
+    assert_eq!(stock.map(|v| v.code), Some("600519".to_string()));
+    assert_eq!(flaky.0.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn retry_on_empty() {
+    let only_error = RetryConfig::default().with_retry_on_error(true);
+    let empty = Empty::default();
+
+    assert!(run_with_retry(&empty, only_error).await.is_none());
+    assert_eq!(empty.0.load(Ordering::SeqCst), 1);
+
+    let only_empty = RetryConfig::default().with_retry_on_empty(true);
+    let empty = Empty::default();
+
+    assert!(run_with_retry(&empty, only_empty).await.is_none());
+    assert_eq!(empty.0.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn codes_passthrough_skip_the_search() {
+    let keywords = vec![
+        "招商银行".to_string(),
+        "600519".to_string(),
+        "00700".to_string(),
+    ];
+    let empty = Empty::default();
+    let results = Batch::default()
+        .with_passthrough(true)
+        .run(&empty, Input::default(), keywords, &mut |_: Progress<
+            '_,
+        >| {
+            ControlFlow::Continue(())
+        })
+        .await;
+
+    assert_eq!(empty.0.load(Ordering::SeqCst), 1);
+    assert!(results[0].1.is_none());
+
+    let codes: Vec<_> = results[1..]
+        .iter()
+        .filter_map(|(_, stock)| stock.as_ref())
+        .map(|v| (v.code.as_str(), v.exchange, v.name.as_str()))
+        .collect();
+
+    assert_eq!(
+        codes,
+        vec![
+            ("600519", Exchange::ShangHai, ""),
+            ("00700", Exchange::HongKong, "")
+        ]
+    );
+}
+
+#[tokio::test]
+async fn failed_tell_the_missing_apart() {
+    let mut missing = vec![];
+    let mut reporter = |progress: Progress<'_>| {
+        if let Progress::Failed { missing: v, .. } = progress {
+            missing.push(v);
+        }
+        ControlFlow::Continue(())
+    };
+    let batch = Batch::default();
+
+    batch
+        .run(
+            &Empty::default(),
+            Input::default(),
+            vec!["不存在".to_string()],
+            &mut reporter,
+        )
+        .await;
+    batch
+        .run(
+            &Flaky::default(),
+            Input::default(),
+            vec!["茅台".to_string()],
+            &mut reporter,
+        )
+        .await;
+    assert_eq!(missing, [true, false]);
+}
+
+/// A source takes longer to search the earlier keywords, the searches complete
+/// in the reversed order.
+struct Reversed;
+
+impl Search for Reversed {
+    type Input = Input;
+    type Output = Stock;
+
+    async fn search_all(&self, input: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
+        let (delay, code, exchange) = match input.0.as_str() {
+            "贵州茅台" => (60, "600519", Exchange::ShangHai),
+            "平安银行" => (30, "000001", Exchange::ShenZhen),
+            _ => (0, "601318", Exchange::ShangHai),
+        };
+
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        Ok(vec![Stock::new(input.0.clone(), code.into(), exchange)])
+    }
+}
+
+#[tokio::test]
+async fn concurrent_results_keep_the_input_order() {
+    let keywords = vec![
+        "贵州茅台".to_string(),
+        "平安银行".to_string(),
+        "中国平安".to_string(),
+    ];
+    let mut completed = vec![];
+    let mut reporter = |progress: Progress<'_>| {
+        if let Progress::Resolved { index, .. } = progress {
+            completed.push(index);
+        }
+        ControlFlow::Continue(())
+    };
+    let results = Batch::default()
+        .with_concurrency(3)
+        .run(&Reversed, Input::default(), keywords.clone(), &mut reporter)
+        .await;
+
+    assert_eq!(completed, vec![2, 1, 0]);
+
+    let codes: Vec<_> = results
+        .iter()
+        .map(|(keyword, stock)| (keyword.as_str(), stock.as_ref().map(|v| v.code.as_str())))
+        .collect();
+
+    assert_eq!(
+        codes,
+        vec![
+            ("贵州茅台", Some("600519")),
+            ("平安银行", Some("000001")),
+            ("中国平安", Some("601318"))
+        ]
+    );
+}
+
+#[tokio::test]
+async fn grade_the_selected_stocks() {
+    let keywords = vec!["招商银行".to_string(), "600519".to_string()];
+    let results = Batch::default()
+        .with_passthrough(true)
+        .run(
+            &DualListed,
+            Input::default(),
+            keywords,
+            &mut |_: Progress<'_>| ControlFlow::Continue(()),
+        )
+        .await;
+    let confidences: Vec<_> = results
+        .iter()
+        .map(|(_, stock)| stock.as_ref().and_then(|v| v.confidence))
+        .collect();
+
+    assert_eq!(
+        confidences,
+        vec![Some(Confidence::High), Some(Confidence::Low)]
+    );
+}
+
+#[tokio::test]
+async fn report_the_resolved_and_failed() {
+    let keywords = vec!["招商银行".to_string(), "不存在".to_string()];
+    let report = Batch::default()
+        .run_report(
+            &DualListed,
+            Input::default(),
+            keywords,
+            &mut |_: Progress<'_>| ControlFlow::Continue(()),
+        )
+        .await;
+
+    assert_eq!(report.resolved.len(), 1);
+    assert_eq!(report.resolved[0].0, "招商银行");
+    assert_eq!(report.resolved[0].1.code, "600036");
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, "不存在");
+    assert!(!report.failed[0].1.message.is_empty());
+
+    let json = report.to_json().unwrap();
+    let parsed: BatchReport = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(parsed.resolved[0].1.code, "600036");
+    assert_eq!(parsed.failed, report.failed);
+    assert_eq!(parsed.elapsed, report.elapsed);
+}
@@ -0,0 +1,117 @@
+use search::export::ExportFormat;
+use search::{Exchange, NormalizeStyle};
+
+#[test]
+fn parse_block_file() -> color_eyre::Result<()> {
+    let text = std::fs::read_to_string("tests/fixtures/sample.blk")?;
+    let stocks = search::block::parse(&text)?;
+    let codes: Vec<_> = stocks
+        .iter()
+        .map(|v| (v.code.as_str(), v.exchange))
+        .collect();
+
+    assert_eq!(
+        codes,
+        [
+            ("600519", Exchange::ShangHai),
+            ("000001", Exchange::ShenZhen),
+            ("300750", Exchange::ShenZhen),
+            ("601318", Exchange::ShangHai),
+            ("830799", Exchange::BeiJing),
+            ("00700", Exchange::HongKong),
+            ("AAPL", Exchange::UnitedStates),
+        ]
+    );
+    assert!(stocks.iter().all(|v| v.name.is_empty()));
+    Ok(())
+}
+
+#[test]
+fn parse_block_plain_codes() -> color_eyre::Result<()> {
+    let stocks = search::block::parse("# watch list\n600519\n\n00700\n")?;
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].exchange, Exchange::ShangHai);
+    assert_eq!(stocks[1].exchange, Exchange::HongKong);
+    Ok(())
+}
+
+#[test]
+fn parse_block_hk_only_in_tdx() -> color_eyre::Result<()> {
+    let err = search::block::parse("600519\n510300\n").unwrap_err();
+
+    assert!(err.to_string().contains("at line 2"));
+    assert!(search::block::parse_code("510300").is_err());
+    assert!(search::block::parse_code("500700").is_err());
+    assert!(!search::block::is_tdx("600519\n510300\n"));
+
+    let stocks = search::block::parse("\r\n500700\r\n")?;
+
+    assert_eq!(stocks[0].code, "00700");
+    assert_eq!(stocks[0].exchange, Exchange::HongKong);
+    Ok(())
+}
+
+#[test]
+fn parse_block_reports_line() {
+    let err = search::block::parse("1600519\nabc\n").unwrap_err();
+
+    assert!(err.to_string().contains("at line 2"));
+}
+
+#[test]
+fn block_round_trip_styles() -> color_eyre::Result<()> {
+    let stocks = search::block::parse("1600519\r\n500700\r\n")?;
+
+    assert_eq!(
+        ExportFormat::Ebk.export_with(&stocks, NormalizeStyle::Prefix)?,
+        "\r\nsh600519\r\nhk00700\r\n"
+    );
+    assert_eq!(
+        ExportFormat::Ebk.export_with(&stocks, NormalizeStyle::Suffix)?,
+        "\r\n600519.SH\r\n00700.HK\r\n"
+    );
+    assert_eq!(
+        ExportFormat::Ebk.export_with(&stocks, NormalizeStyle::Plain)?,
+        "\r\n600519\r\n00700\r\n"
+    );
+
+    let again = search::block::parse(&ExportFormat::Ebk.export(&stocks)?)?;
+
+    assert_eq!(again[0].normalize(), "1600519");
+    assert_eq!(again[1].normalize(), "500700");
+    Ok(())
+}
+
+#[test]
+fn merge_block_files() -> color_eyre::Result<()> {
+    let dir = std::env::temp_dir().join(format!("stockconverter-merge-{}", std::process::id()));
+
+    std::fs::create_dir_all(&dir)?;
+
+    let first = dir.join("first.ebk");
+    let second = dir.join("second.blk");
+
+    std::fs::write(&first, "\r\n1600519\r\n0000001\r\n")?;
+    std::fs::write(&second, "sz000001\n600519.SH\n00700.HK\n")?;
+
+    let stocks = search::block::merge(&[&first, &second])?;
+    let codes: Vec<_> = stocks.iter().map(|v| v.normalize()).collect();
+
+    assert_eq!(codes, ["1600519", "0000001", "500700"]);
+    assert!(search::block::merge(&[dir.join("missing.ebk")]).is_err());
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn check_block_lines() {
+    let invalid = search::block::check("\r\n1600519\r\n0600519\r\nabc\r\n# note\r\n500700\r\n");
+    let lines: Vec<_> = invalid
+        .iter()
+        .map(|v| (v.line, v.content.as_str()))
+        .collect();
+
+    assert_eq!(lines, [(3, "0600519"), (4, "abc")]);
+    assert!(search::block::check("1600519\n500700\n").is_empty());
+}
@@ -0,0 +1,18 @@
+use search::chinese::{simplify, traditionalize};
+
+#[test]
+fn simplify_traditional_names() {
+    assert_eq!(simplify("中國平安"), "中国平安");
+    assert_eq!(simplify("長江電力"), "长江电力");
+    assert_eq!(simplify("騰訊控股"), "腾讯控股");
+    assert_eq!(simplify("貴州茅台"), "贵州茅台");
+    assert_eq!(simplify("平安银行"), "平安银行");
+}
+
+#[test]
+fn traditionalize_simplified_names() {
+    assert_eq!(traditionalize("中国平安"), "中國平安");
+    assert_eq!(traditionalize("腾讯控股"), "騰訊控股");
+    // 台 is also used in traditional text
+    assert_eq!(traditionalize("贵州茅台"), "貴州茅台");
+}
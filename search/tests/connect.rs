@@ -0,0 +1,37 @@
+use search::connect::{ConnectList, guess_eligible};
+use search::{Exchange, Stock};
+
+#[test]
+fn connect_list_is_authoritative() -> color_eyre::Result<()> {
+    let list = ConnectList::parse("# 沪股通\n600519\nsz000001\n00700.HK\n")?;
+    let mut stocks = vec![
+        Stock::new("贵州茅台".into(), "600519".into(), Exchange::ShangHai),
+        Stock::new("平安银行".into(), "000001".into(), Exchange::ShenZhen),
+        Stock::new("腾讯控股".into(), "00700".into(), Exchange::HongKong),
+        Stock::new("万科A".into(), "000002".into(), Exchange::ShenZhen),
+    ];
+
+    list.apply(&mut stocks);
+
+    let eligible: Vec<_> = stocks.iter().map(|v| v.connect_eligible).collect();
+
+    assert_eq!(
+        eligible,
+        vec![Some(true), Some(true), Some(true), Some(false)]
+    );
+    Ok(())
+}
+
+#[test]
+fn guess_rules_out_the_obvious() {
+    let guess = |name: &str, code: &str, exchange| {
+        guess_eligible(&Stock::new(name.into(), code.into(), exchange))
+    };
+
+    assert_eq!(guess("*ST天山", "300313", Exchange::ShenZhen), Some(false));
+    assert_eq!(guess("贝特瑞", "835185", Exchange::BeiJing), Some(false));
+    assert_eq!(guess("苹果", "AAPL", Exchange::UnitedStates), Some(false));
+    // unknown without the list
+    assert_eq!(guess("贵州茅台", "600519", Exchange::ShangHai), None);
+    assert_eq!(guess("腾讯控股", "00700", Exchange::HongKong), None);
+}
@@ -0,0 +1,158 @@
+use search::export::{ExportFormat, OutputEncoding};
+use search::{Exchange, Stock};
+
+#[test]
+fn export_ebk_bytes() -> color_eyre::Result<()> {
+    let stocks = vec![
+        Stock::new("贵州茅台".into(), "600519".into(), Exchange::ShangHai),
+        Stock::new("平安银行".into(), "000001".into(), Exchange::ShenZhen),
+    ];
+    let content = ExportFormat::Ebk.export(&stocks)?;
+
+    assert_eq!(content.as_bytes(), b"\r\n1600519\r\n0000001\r\n");
+    assert!(content.is_ascii());
+    Ok(())
+}
+
+#[test]
+fn export_table_aligns_cjk() -> color_eyre::Result<()> {
+    let stocks = vec![
+        Stock::new("贵州茅台".into(), "600519".into(), Exchange::ShangHai),
+        Stock::new("TCL科技".into(), "000100".into(), Exchange::ShenZhen),
+        Stock::new("Ａ股".into(), "00700".into(), Exchange::HongKong),
+    ];
+    let content = ExportFormat::Table.export(&stocks)?;
+    let lines: Vec<_> = content.lines().collect();
+
+    assert_eq!(search::export::display_width("贵州茅台"), 8);
+    assert_eq!(search::export::display_width("TCL科技"), 7);
+    assert_eq!(search::export::display_width("Ａ股"), 4);
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "name      code    exchange  normalized");
+    assert_eq!(lines[1], "贵州茅台  600519  ShangHai  1600519");
+    assert_eq!(lines[2], "TCL科技   000100  ShenZhen  0000100");
+    assert_eq!(lines[3], "Ａ股      00700   HongKong  500700");
+    Ok(())
+}
+
+#[test]
+fn export_table_query_column() -> color_eyre::Result<()> {
+    let mut stock = Stock::new("贵州茅台".into(), "600519".into(), Exchange::ShangHai);
+
+    stock.query = Some("茅台".into());
+
+    let content = ExportFormat::Table.export(&[stock])?;
+    let lines: Vec<_> = content.lines().collect();
+
+    assert!(lines[0].ends_with("normalized  query"));
+    assert!(lines[1].ends_with("1600519     茅台"));
+    Ok(())
+}
+
+#[test]
+fn export_csv_in_gbk() -> color_eyre::Result<()> {
+    let stocks = vec![Stock::new(
+        "贵州茅台".into(),
+        "600519".into(),
+        Exchange::ShangHai,
+    )];
+    let content = ExportFormat::Csv.export(&stocks)?;
+    let bytes = OutputEncoding::Gbk.encode(&content)?;
+
+    // 贵 is `B9F3` in GBK
+    assert!(bytes.windows(2).any(|v| v == [0xB9, 0xF3]));
+    assert!(std::str::from_utf8(&bytes).is_err());
+    assert_eq!(OutputEncoding::Gbk.decode(&bytes), content);
+    assert_eq!(OutputEncoding::Utf8.encode(&content)?, content.as_bytes());
+    assert!(OutputEncoding::Gbk.encode("😀").is_err());
+    Ok(())
+}
+
+#[test]
+fn export_placeholders_of_missing() -> color_eyre::Result<()> {
+    let results = vec![
+        (
+            "茅台".to_string(),
+            vec![Stock::new(
+                "贵州茅台".into(),
+                "600519".into(),
+                Exchange::ShangHai,
+            )],
+        ),
+        ("不存在".to_string(), vec![]),
+        (
+            "平安银行".to_string(),
+            vec![Stock::new(
+                "平安银行".into(),
+                "000001".into(),
+                Exchange::ShenZhen,
+            )],
+        ),
+    ];
+    let style = search::NormalizeStyle::default();
+
+    assert_eq!(
+        ExportFormat::Ebk.export_with_placeholders(&results, style)?,
+        "\r\n1600519\r\n# 不存在: not found\r\n0000001\r\n"
+    );
+
+    let csv = ExportFormat::Csv.export_with_placeholders(&results, style)?;
+
+    assert_eq!(csv.lines().count(), 4);
+    assert_eq!(csv.lines().nth(2), Some("# 不存在: not found"));
+    let ndjson = ExportFormat::Ndjson.export_with_placeholders(&results, style)?;
+    let missing = ndjson.lines().nth(1).unwrap_or_default();
+
+    assert!(missing.contains(r#""query":"不存在""#));
+    assert!(missing.contains(r#""error":"not found""#));
+    assert!(
+        ExportFormat::Json
+            .export_with_placeholders(&results, style)
+            .is_err()
+    );
+    Ok(())
+}
+
+#[test]
+fn export_explore_groups() {
+    let results = vec![
+        (
+            "中国".to_string(),
+            vec![
+                Stock::new("中国平安".into(), "601318".into(), Exchange::ShangHai),
+                Stock::new("中国中免".into(), "601888".into(), Exchange::ShangHai),
+            ],
+        ),
+        ("不存在".to_string(), vec![]),
+    ];
+
+    assert_eq!(
+        search::export::explore(&results),
+        "中国 (2)\n  中国平安  601318  ShangHai\n  中国中免  601888  ShangHai\n不存在 (0)\n"
+    );
+}
+
+#[test]
+fn export_comment_per_format() -> color_eyre::Result<()> {
+    let stocks = vec![Stock::new(
+        "贵州茅台".into(),
+        "600519".into(),
+        Exchange::ShangHai,
+    )];
+    let comment = search::export::generated_comment("cninfo", stocks.len());
+
+    assert!(comment.starts_with("generated by stockconverter at "));
+    assert!(comment.ends_with(", tool=cninfo, count=1"));
+    for format in ExportFormat::ALL {
+        let content = format.export(&stocks)?;
+        let commented = format.with_comment(content.clone(), &comment);
+
+        match format {
+            ExportFormat::Csv | ExportFormat::TxtNames | ExportFormat::Table => {
+                assert_eq!(commented, format!("# {comment}\n{content}"));
+            }
+            _ => assert_eq!(commented, content),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use search::Exchange;
+
+fn utc(text: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(text)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+#[test]
+fn timezone_of_exchanges() {
+    assert_eq!(Exchange::ShangHai.timezone(), chrono_tz::Asia::Shanghai);
+    assert_eq!(Exchange::BeiJing.timezone(), chrono_tz::Asia::Shanghai);
+    assert_eq!(Exchange::HongKong.timezone(), chrono_tz::Asia::Hong_Kong);
+    assert_eq!(
+        Exchange::UnitedStates.timezone(),
+        chrono_tz::America::New_York
+    );
+}
+
+#[test]
+fn mainland_trading_hours() {
+    // 2025-06-04 is a wednesday
+    assert!(Exchange::ShangHai.is_trading_at(&utc("2025-06-04T10:00:00+08:00"), &[]));
+    assert!(!Exchange::ShangHai.is_trading_at(&utc("2025-06-04T09:15:00+08:00"), &[]));
+    // the lunch break
+    assert!(!Exchange::ShenZhen.is_trading_at(&utc("2025-06-04T12:00:00+08:00"), &[]));
+    assert!(Exchange::ShenZhen.is_trading_at(&utc("2025-06-04T14:59:00+08:00"), &[]));
+    assert!(!Exchange::ShenZhen.is_trading_at(&utc("2025-06-04T15:00:00+08:00"), &[]));
+    // saturday
+    assert!(!Exchange::ShangHai.is_trading_at(&utc("2025-06-07T10:00:00+08:00"), &[]));
+}
+
+#[test]
+fn hong_kong_and_us_trading_hours() {
+    // the mainland is closed but hong kong is open
+    let time = utc("2025-06-04T15:30:00+08:00");
+
+    assert!(!Exchange::ShangHai.is_trading_at(&time, &[]));
+    assert!(Exchange::HongKong.is_trading_at(&time, &[]));
+    assert!(!Exchange::HongKong.is_trading_at(&utc("2025-06-04T12:30:00+08:00"), &[]));
+    // 10:00 in new york, in the daylight saving time
+    assert!(Exchange::UnitedStates.is_trading_at(&utc("2025-06-04T14:00:00Z"), &[]));
+    assert!(!Exchange::UnitedStates.is_trading_at(&utc("2025-06-04T21:00:00Z"), &[]));
+}
+
+#[test]
+fn skip_holidays() {
+    let time = chrono_tz::Asia::Shanghai
+        .with_ymd_and_hms(2025, 10, 1, 10, 0, 0)
+        .unwrap();
+    let holidays = [NaiveDate::from_ymd_opt(2025, 10, 1).unwrap()];
+
+    assert!(Exchange::ShangHai.is_trading_at(&time, &[]));
+    assert!(!Exchange::ShangHai.is_trading_at(&time, &holidays));
+}
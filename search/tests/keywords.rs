@@ -0,0 +1,38 @@
+#[test]
+fn split_mixed_separators() {
+    let keywords = search::split_keywords(
+        "平安银行,贵州茅台，中国平安、 腾讯控股;招商银行；\n 600519 \n\n",
+        search::SPLIT_CHARS,
+    );
+
+    assert_eq!(
+        keywords,
+        [
+            "平安银行",
+            "贵州茅台",
+            "中国平安",
+            "腾讯控股",
+            "招商银行",
+            "600519"
+        ]
+    );
+}
+
+#[test]
+fn split_disabled() {
+    let keywords = search::split_keywords("平安银行,贵州茅台\n中国平安", "");
+
+    assert_eq!(keywords, ["平安银行,贵州茅台", "中国平安"]);
+}
+
+#[test]
+fn convert_full_width_codes() {
+    let keywords = search::split_keywords("６００５１９，ＡＡＰＬ、招商银行", search::SPLIT_CHARS);
+
+    assert_eq!(keywords, ["600519", "AAPL", "招商银行"]);
+    assert_eq!(
+        search::normalize_stock_number("６００５１９").as_deref(),
+        Some("1600519")
+    );
+    assert_eq!(search::half_width("０７００．ＨＫ"), "0700．HK");
+}
@@ -0,0 +1,96 @@
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use search::batch::{Batch, Progress};
+use search::offline::Offline;
+use search::{Exchange, QueryInput, Search, Stock};
+
+#[derive(Debug, Clone, Default)]
+struct Input(String);
+
+impl QueryInput for Input {
+    fn set_keyword(&mut self, keyword: String) {
+        self.0 = keyword;
+    }
+
+    fn keyword(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A source resolves any keyword to 平安银行, count the searches.
+#[derive(Default)]
+struct Counted(AtomicUsize);
+
+impl Search for Counted {
+    type Input = Input;
+    type Output = Stock;
+
+    async fn search_all(&self, input: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(vec![Stock::new(
+            input.0.clone(),
+            "000001".into(),
+            Exchange::ShenZhen,
+        )])
+    }
+}
+
+#[test]
+fn parse_offline_table() -> color_eyre::Result<()> {
+    let offline = Offline::parse_csv("# name,code\n贵州茅台,600519.SH\n\n腾讯控股，00700.HK\n")?;
+
+    assert_eq!(offline.len(), 2);
+    assert_eq!(
+        offline.get(" 贵州茅台 ").map(|v| v.normalize()),
+        Some("1600519".into())
+    );
+    assert_eq!(
+        offline.get("腾讯控股").map(|v| v.exchange),
+        Some(Exchange::HongKong)
+    );
+    assert!(offline.get("平安银行").is_none());
+
+    let err = Offline::parse_csv("贵州茅台\n").unwrap_err();
+
+    assert!(err.to_string().contains("at line 1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn offline_hits_skip_the_search() -> color_eyre::Result<()> {
+    let tool = Counted::default();
+    let keywords = vec!["贵州茅台".to_string(), "平安银行".to_string()];
+    let results = Batch::default()
+        .with_offline(Offline::parse_csv("贵州茅台,600519.SH\n")?)
+        .run(&tool, Input::default(), keywords, &mut |_: Progress<'_>| {
+            ControlFlow::Continue(())
+        })
+        .await;
+    let codes: Vec<_> = results
+        .iter()
+        .filter_map(|(_, stock)| stock.as_ref())
+        .map(|v| v.code.as_str())
+        .collect();
+
+    // only the miss is searched
+    assert_eq!(tool.0.load(Ordering::SeqCst), 1);
+    assert_eq!(codes, ["600519", "000001"]);
+    Ok(())
+}
+
+#[cfg(feature = "offline")]
+#[test]
+fn bundled_offline_table() -> color_eyre::Result<()> {
+    let offline = Offline::bundled()?;
+
+    assert_eq!(
+        offline.get("贵州茅台").map(|v| v.code.as_str()),
+        Some("600519")
+    );
+    assert_eq!(
+        offline.get("腾讯控股").map(|v| v.code.as_str()),
+        Some("00700")
+    );
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use search::overrides::Overrides;
+use search::{Exchange, SearchOptions, Stock};
+
+#[test]
+fn override_flips_exchange() -> color_eyre::Result<()> {
+    let overrides = Overrides::parse_csv("# code,exchange\n600519,sz\n900901,sh\n")?;
+
+    assert_eq!(overrides.normalize("600519").as_deref(), Some("0600519"));
+    assert_eq!(overrides.normalize("000001").as_deref(), Some("0000001"));
+    assert_eq!(search::normalize_stock_number("900901"), None);
+    assert_eq!(overrides.normalize("900901").as_deref(), Some("1900901"));
+    assert_eq!(overrides.stock("900901")?.exchange, Exchange::ShangHai);
+    Ok(())
+}
+
+#[test]
+fn override_applies_before_hint() -> color_eyre::Result<()> {
+    let overrides = Overrides::parse_json(r#"{"600519": "sz"}"#)?;
+    let options = SearchOptions::default().with_overrides(overrides);
+    let stock = Stock::new("贵州茅台".into(), "600519".into(), Exchange::ShangHai);
+
+    assert_eq!(
+        options.accept(stock).map(|v| v.exchange),
+        Some(Exchange::ShenZhen)
+    );
+    Ok(())
+}
@@ -0,0 +1,231 @@
+use search::{Exchange, SecurityKind, Stock};
+
+fn convert<T: TryInto<Stock>>(outputs: Vec<T>) -> Vec<Stock> {
+    outputs
+        .into_iter()
+        .filter_map(|v| v.try_into().ok())
+        .collect()
+}
+
+#[test]
+fn parse_cninfo() -> color_eyre::Result<()> {
+    let stocks = convert(search::cninfo::parse(include_str!("fixtures/cninfo.json"))?);
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].code, "600519");
+    assert_eq!(stocks[0].name, "贵州茅台");
+    assert_eq!(stocks[0].exchange, Exchange::ShangHai);
+    assert_eq!(stocks[1].code, "00700");
+    assert_eq!(stocks[1].exchange, Exchange::HongKong);
+    Ok(())
+}
+
+#[test]
+fn parse_cninfo_wrapped() -> color_eyre::Result<()> {
+    let array = include_str!("fixtures/cninfo.json");
+    let wrapped = format!(r#"{{"keyBoardList":{array}}}"#);
+    let stocks = convert(search::cninfo::parse(&wrapped)?);
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].code, "600519");
+    assert_eq!(stocks[1].exchange, Exchange::HongKong);
+    assert!(search::cninfo::parse(r#"{"other":[]}"#).is_err());
+    Ok(())
+}
+
+#[test]
+fn parse_cninfo_full_record() -> color_eyre::Result<()> {
+    let outputs = search::cninfo::parse(include_str!("fixtures/cninfo.json"))?;
+    let output = &outputs[0];
+
+    assert_eq!(output.code(), "600519");
+    assert_eq!(output.name(), "贵州茅台");
+    assert_eq!(output.exchange(), search::cninfo::TYPE_SHJ);
+    assert_eq!(output.org_id(), "gssh0600519");
+    assert_eq!(output.pinyin(), "gzmt");
+    assert_eq!(output.category(), "A股");
+    Ok(())
+}
+
+#[test]
+fn parse_cninfo_industry() -> color_eyre::Result<()> {
+    let industry = search::cninfo::parse_industry(include_str!("fixtures/cninfo_company.json"))?;
+
+    assert_eq!(industry.as_deref(), Some("酒、饮料和精制茶制造业"));
+    assert_eq!(
+        search::cninfo::parse_industry(r#"{"data":{"records":[]}}"#)?,
+        None
+    );
+    Ok(())
+}
+
+#[test]
+fn parse_sina() -> color_eyre::Result<()> {
+    let stocks = convert(search::sina::parse(include_str!("fixtures/sina.txt"))?);
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].code, "600519");
+    assert_eq!(stocks[0].name, "贵州茅台");
+    assert_eq!(stocks[1].code, "00700");
+    assert_eq!(stocks[1].exchange, Exchange::HongKong);
+    Ok(())
+}
+
+#[test]
+fn parse_sina_trailing_semicolon() -> color_eyre::Result<()> {
+    let text = r#"var suggestdata_1700000000000="贵州茅台,11,600519,sh600519,贵州茅台,,贵州茅台,99,1,,,;;腾讯控股,31,00700,00700,腾讯控股,,腾讯控股,99,1,,,;";"#;
+    let stocks = convert(search::sina::parse(text)?);
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].code, "600519");
+    assert_eq!(stocks[1].code, "00700");
+    Ok(())
+}
+
+#[test]
+fn parse_sina_empty() -> color_eyre::Result<()> {
+    let outputs = search::sina::parse(r#"var suggestdata_1700000000000="";"#)?;
+
+    assert!(outputs.is_empty());
+    Ok(())
+}
+
+#[test]
+fn parse_cfi_lazily() -> color_eyre::Result<()> {
+    let text = include_str!("fixtures/cfi.html");
+    let eager = search::cfi::parse(text)?;
+    let mut lazy = search::cfi::parse_iter(text);
+    let first = lazy.next().transpose()?.map(Stock::try_from).transpose()?;
+
+    assert_eq!(first.map(|v| v.code), Some("600519".into()));
+    assert_eq!(lazy.count() + 1, eager.len());
+    Ok(())
+}
+
+#[test]
+fn parse_cfi() -> color_eyre::Result<()> {
+    let stocks = convert(search::cfi::parse(include_str!("fixtures/cfi.html"))?);
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].code, "600519");
+    assert_eq!(stocks[0].name, "贵州茅台");
+    assert_eq!(stocks[0].exchange, Exchange::ShangHai);
+    assert_eq!(stocks[1].code, "600600");
+    assert_eq!(stocks[1].name, "青岛啤酒");
+    Ok(())
+}
+
+#[test]
+fn parse_cfi_partial() -> color_eyre::Result<()> {
+    let stocks = convert(search::cfi::parse(include_str!(
+        "fixtures/cfi_partial.html"
+    ))?);
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].code, "600519");
+    assert_eq!(stocks[0].name, "贵州茅台");
+    assert_eq!(stocks[1].code, "000002");
+    assert_eq!(stocks[1].name, "万科Ａ");
+    Ok(())
+}
+
+#[test]
+fn parse_hexun() -> color_eyre::Result<()> {
+    let stocks = convert(search::hexun::parse(include_str!("fixtures/hexun.txt"))?);
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].code, "600519");
+    assert_eq!(stocks[1].code, "000001");
+    assert_eq!(stocks[1].name, "平安银行");
+    assert_eq!(stocks[1].exchange, Exchange::ShenZhen);
+    Ok(())
+}
+
+#[test]
+fn parse_hexun_hk_us() -> color_eyre::Result<()> {
+    let stocks = convert(search::hexun::parse(include_str!(
+        "fixtures/hexun_hk_us.txt"
+    ))?);
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].code, "00700");
+    assert_eq!(stocks[0].exchange, Exchange::HongKong);
+    assert_eq!(stocks[1].code, "BABA");
+    assert_eq!(stocks[1].exchange, Exchange::UnitedStates);
+    assert!(stocks[1].validate().is_ok());
+    Ok(())
+}
+
+#[test]
+fn parse_sohu() -> color_eyre::Result<()> {
+    let stocks = convert(search::sohu::parse(include_str!("fixtures/sohu.txt"))?);
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].code, "600519");
+    assert_eq!(stocks[0].name, "贵州茅台");
+    assert_eq!(stocks[1].exchange, Exchange::HongKong);
+    Ok(())
+}
+
+#[test]
+fn parse_sohu_parenthesis_in_name() -> color_eyre::Result<()> {
+    let text = r#"searchBox1.output({"result":[["us","BABA","阿里巴巴(ADR)ALBB","0"],["cn","600519","贵州茅台)GZMT","0"]]});"#;
+    let outputs = search::sohu::parse(text)?;
+
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(
+        search::sohu::unwrap_callback("searchBox1.output({\"a\":\"(b)\"})")?,
+        "{\"a\":\"(b)\"}"
+    );
+    assert!(search::sohu::parse("searchBox1.output(").is_err());
+    Ok(())
+}
+
+#[test]
+fn parse_sohu_index() -> color_eyre::Result<()> {
+    let stocks = convert(search::sohu::parse(include_str!(
+        "fixtures/sohu_index.txt"
+    ))?);
+
+    assert_eq!(stocks.len(), 4);
+    assert_eq!(stocks[0].name, "上证指数");
+    assert_eq!(stocks[0].kind, SecurityKind::Index);
+    assert_eq!(stocks[0].exchange, Exchange::ShangHai);
+    assert_eq!(stocks[0].normalize(), "1000001");
+    assert_eq!(stocks[1].exchange, Exchange::ShenZhen);
+    assert_eq!(stocks[1].normalize(), "0399001");
+    assert_eq!(stocks[2].kind, SecurityKind::Index);
+    assert_eq!(stocks[3].kind, SecurityKind::Stock);
+    assert_eq!(stocks[3].normalize(), "0000001");
+    assert!(stocks.iter().all(|v| v.validate().is_ok()));
+    Ok(())
+}
+
+#[test]
+fn parse_error_with_context() {
+    let text = "  <html>贵州茅台 is moved</html>";
+    let err = search::sina::parse(text).unwrap_err().to_string();
+
+    assert!(err.contains("sina at offset 2"), "{err}");
+    assert!(err.contains("<html>贵州茅台"), "{err}");
+
+    // the offset is where the quoted rows are expected
+    let text = "var suggestdata_1=贵州茅台,11,600519,sh600519;";
+    let offset = text.find('=').unwrap();
+    let err = search::sina::parse(text).unwrap_err().to_string();
+
+    assert!(err.contains(&format!("sina at offset {offset}")), "{err}");
+
+    // the broken row in the middle is skipped
+    let text = "var suggestdata_1=\"贵州茅台,11,600519,sh600519;坏行;腾讯控股,31,00700,00700\";";
+    let stocks = convert(search::sina::parse(text).unwrap());
+    let codes: Vec<_> = stocks.iter().map(|v| v.code.as_str()).collect();
+
+    assert_eq!(codes, ["600519", "00700"]);
+
+    // the snippet never splits a character
+    let text = "贵".repeat(40);
+
+    assert_eq!(search::snippet(&text, 31), "贵".repeat(21));
+    assert_eq!(search::snippet("short", 100), "");
+}
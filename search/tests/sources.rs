@@ -0,0 +1,407 @@
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use search::all::{All, Input, Source};
+use search::batch::{Batch, Progress};
+use search::limiter::RateLimiter;
+use search::probe::Probe;
+use search::{DynSearch, Search, Tool, cfi, cninfo, hexun, selftest, sina, sohu};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+
+#[test]
+fn sources_are_shareable() {
+    assert_shareable::<cninfo::CnInfo>();
+    assert_shareable::<sina::Sina>();
+    assert_shareable::<cfi::Cfi>();
+    assert_shareable::<hexun::Hexun>();
+    assert_shareable::<sohu::SoHu>();
+}
+
+/// Serve the body once on a local port, return the base url.
+async fn serve_once(body: &'static str) -> color_eyre::Result<String> {
+    serve_bytes_once("", body.as_bytes()).await
+}
+
+/// Serve the body with the extra `headers` once on a local port, return the base url.
+async fn serve_bytes_once(
+    headers: &'static str,
+    body: &'static [u8],
+) -> color_eyre::Result<String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        if let Ok((mut stream, _)) = listener.accept().await {
+            let mut buff = [0; 1024];
+            let _ = stream.read(&mut buff).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.write_all(body).await;
+        }
+    });
+    Ok(format!("http://{addr}/"))
+}
+
+#[tokio::test]
+async fn search_with_base_url() -> color_eyre::Result<()> {
+    let base_url = serve_once(include_str!("fixtures/sina.txt")).await?;
+    let tool = sina::Sina::init_with_probe(reqwest::Client::new(), &Probe::Skip)
+        .await?
+        .with_base_url(base_url.clone());
+
+    assert_eq!(format!("{}/", tool.base_url), base_url);
+
+    let stock = tool.search(&sina::Input::new("茅台")).await?;
+
+    assert_eq!(stock.code, "600519");
+    Ok(())
+}
+
+#[tokio::test]
+async fn decode_gzip_body() -> color_eyre::Result<()> {
+    let gzip = include_bytes!("fixtures/sina.txt.gz");
+    let base_url = serve_bytes_once("Content-Encoding: gzip\r\n", gzip).await?;
+    let client = search::decompression(reqwest::Client::builder()).build()?;
+    let res = client.get(base_url).send().await?;
+    let text = search::response_text(res, "sina").await?;
+
+    assert_eq!(text, include_str!("fixtures/sina.txt"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_undecoded_body() -> color_eyre::Result<()> {
+    let gzip = include_bytes!("fixtures/sina.txt.gz");
+    let base_url = serve_bytes_once("Content-Encoding: gzip\r\n", gzip).await?;
+    let client = reqwest::Client::builder().no_gzip().build()?;
+    let res = client.get(base_url).send().await?;
+    let err = search::response_text(res, "sina").await.unwrap_err();
+
+    assert!(err.to_string().contains("not decoded"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn read_timeout_of_slow_endpoint() -> color_eyre::Result<()> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    // accept the connection but never respond
+    tokio::spawn(async move {
+        if let Ok((stream, _)) = listener.accept().await {
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            drop(stream);
+        }
+    });
+
+    let timeouts = search::Timeouts::default()
+        .with_connect(Some(std::time::Duration::from_millis(500)))
+        .with_read(Some(std::time::Duration::from_millis(100)));
+    let client = timeouts.apply(reqwest::Client::builder()).build()?;
+    let err = client
+        .get(format!("http://{addr}/"))
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(err.is_timeout());
+    Ok(())
+}
+
+#[tokio::test]
+async fn search_cninfo_records() -> color_eyre::Result<()> {
+    let base_url = serve_once(include_str!("fixtures/cninfo.json")).await?;
+    let tool = cninfo::CnInfo::init_with_probe(reqwest::Client::new(), &Probe::Skip)
+        .await?
+        .with_base_url(base_url);
+    let records = tool
+        .search_records(
+            &cninfo::Input::new("腾讯控股"),
+            &search::SearchOptions::default(),
+        )
+        .await?;
+
+    // the exact name goes first, the record is kept with the stock
+    let (stock, output) = &records[0];
+
+    assert_eq!(stock.code, "00700");
+    assert_eq!(output.org_id(), "gshk0000700");
+    assert_eq!(output.pinyin(), "txkg");
+    assert_eq!(output.category(), "港股");
+    assert_eq!(records[1].1.org_id(), "gssh0600519");
+    Ok(())
+}
+
+#[tokio::test]
+async fn stable_all_skips_empty_sources() -> color_eyre::Result<()> {
+    let mut sources = vec![];
+
+    for body in ["var suggestdata=\"\";", include_str!("fixtures/sina.txt")] {
+        let tool = sina::Sina::init_with_probe(reqwest::Client::new(), &Probe::Skip)
+            .await?
+            .with_base_url(serve_once(body).await?);
+
+        sources.push(Source::Sina(tool));
+    }
+    let tool = All {
+        sources,
+        stable: true,
+        ..Default::default()
+    };
+    let stocks = tool.search_all(&Input::new("茅台")).await?;
+
+    assert_eq!(stocks[0].code, "600519");
+    assert_eq!(stocks[0].source, Some(search::Tool::Sina));
+    Ok(())
+}
+
+#[test]
+fn parse_extra_headers() -> color_eyre::Result<()> {
+    let headers = search::extra_headers(
+        &["Referer: https://www.cninfo.com.cn/".to_string()],
+        &["JSESSIONID=abc".to_string(), " route=1 ".to_string()],
+    )?;
+
+    assert_eq!(headers["referer"], "https://www.cninfo.com.cn/");
+    assert_eq!(headers["cookie"], "JSESSIONID=abc; route=1");
+
+    let err = search::extra_headers(&["Referer https://example.com".to_string()], &[]).unwrap_err();
+
+    assert!(err.to_string().contains("expect `Name: value`"));
+    assert!(search::extra_headers(&["Bad Name: v".to_string()], &[]).is_err());
+    assert!(search::extra_headers(&[], &["novalue".to_string()]).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn search_dyn_sources_in_vec() -> color_eyre::Result<()> {
+    let client = reqwest::Client::new();
+    let sina = sina::Sina::init_with_probe(client.clone(), &Probe::Skip)
+        .await?
+        .with_base_url(serve_once(include_str!("fixtures/sina.txt")).await?);
+    let hexun = hexun::Hexun::init_with_probe(client, &Probe::Skip)
+        .await?
+        .with_base_url(serve_once(include_str!("fixtures/hexun_hk_us.txt")).await?);
+    let sources: Vec<Box<dyn DynSearch>> = vec![Box::new(sina), Box::new(hexun)];
+
+    assert_eq!(sources[0].search_dyn("茅台").await?.code, "600519");
+    assert_eq!(sources[1].search_dyn("腾讯").await?.code, "00700");
+    Ok(())
+}
+
+#[tokio::test]
+async fn selftest_cfi_samples() -> color_eyre::Result<()> {
+    let samples = selftest::samples_of(Tool::Cfi);
+
+    assert_eq!(samples.len(), 1);
+    assert_eq!(
+        selftest::samples_of(Tool::Sina).len(),
+        selftest::SAMPLES.len()
+    );
+
+    let tool = cfi::Cfi::init_with_probe(reqwest::Client::new(), &Probe::Skip)
+        .await?
+        .with_base_url(serve_once(include_str!("fixtures/cfi.html")).await?);
+    let outcomes = selftest::run(&Source::Cfi(tool)).await;
+
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].sample.code, "600519");
+    assert!(outcomes[0].passed(), "{:?}", outcomes[0].actual);
+    Ok(())
+}
+
+/// Serve the body for any count of requests, count the requests.
+#[tokio::test]
+async fn all_reports_conversion_errors() -> color_eyre::Result<()> {
+    // the code of the only row belongs to no exchange
+    let body = "var suggestdata_1=\"坏数据,11,999999,sh999999,坏数据,,坏数据,99,1,,,\";";
+
+    for stable in [true, false] {
+        let tool = sina::Sina::init_with_probe(reqwest::Client::new(), &Probe::Skip)
+            .await?
+            .with_base_url(serve_once(body).await?);
+        let tool = All {
+            sources: vec![Source::Sina(tool)],
+            stable,
+            ..Default::default()
+        };
+        let error = tool.search_all(&Input::new("坏数据")).await.unwrap_err();
+
+        assert!(error.to_string().contains("999999"), "{error}");
+    }
+    Ok(())
+}
+
+async fn serve_counted(body: &'static str, count: Arc<AtomicUsize>) -> color_eyre::Result<String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let count = count.clone();
+
+            tokio::spawn(async move {
+                let mut buff = [0; 1024];
+                let _ = stream.read(&mut buff).await;
+                count.fetch_add(1, Ordering::SeqCst);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    Ok(format!("http://{addr}/"))
+}
+
+#[tokio::test]
+async fn shared_limiter_bounds_all_sources() -> color_eyre::Result<()> {
+    let rps = 20.0;
+    let count = Arc::new(AtomicUsize::new(0));
+    let mut sources = vec![];
+
+    // the stable mode tries the empty sources before the last one
+    for body in [
+        "var suggestdata=\"\";",
+        "var suggestdata=\"\";",
+        include_str!("fixtures/sina.txt"),
+    ] {
+        let tool = sina::Sina::init_with_probe(reqwest::Client::new(), &Probe::Skip)
+            .await?
+            .with_base_url(serve_counted(body, count.clone()).await?);
+
+        sources.push(Source::Sina(tool));
+    }
+    let tool = All {
+        sources,
+        stable: true,
+        ..Default::default()
+    }
+    .with_limiter(Some(RateLimiter::new(rps)?));
+    let start = Instant::now();
+
+    for _ in 0..4 {
+        assert_eq!(
+            tool.search_all(&Input::new("茅台")).await?[0].code,
+            "600519"
+        );
+    }
+    let elapsed = start.elapsed();
+    let requests = count.load(Ordering::SeqCst);
+
+    assert_eq!(requests, 12);
+    // the first token is available at once
+    assert!(elapsed >= Duration::from_secs_f64((requests - 1) as f64 / rps));
+    assert!(RateLimiter::new(0.0).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn dump_the_response_body() -> color_eyre::Result<()> {
+    let dir = std::env::temp_dir().join(format!("stockconverter-dump-{}", std::process::id()));
+    let body = include_str!("fixtures/sina.txt");
+    let tool = sina::Sina::init_with_probe(reqwest::Client::new(), &Probe::Skip)
+        .await?
+        .with_base_url(serve_once(body).await?)
+        .with_dump_dir(Some(dir.clone()));
+
+    tool.search(&sina::Input::new("茅台")).await?;
+
+    assert_eq!(std::fs::read_to_string(dir.join("茅台.sina.txt"))?, body);
+    assert_eq!(
+        search::dump_file_name("cfi", " a/b\\c..d "),
+        "a_b_c__d.cfi.txt"
+    );
+    assert_eq!(search::dump_file_name("cfi", ""), "_.cfi.txt");
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+/// Serve the body slowly for any count of requests, record the most in-flight requests.
+async fn serve_tracked(
+    body: &'static str,
+    in_flight: Arc<AtomicUsize>,
+    most: Arc<AtomicUsize>,
+) -> color_eyre::Result<String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let in_flight = in_flight.clone();
+            let most = most.clone();
+
+            tokio::spawn(async move {
+                let mut buff = [0; 1024];
+                let _ = stream.read(&mut buff).await;
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+
+                most.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    Ok(format!("http://{addr}/"))
+}
+
+#[tokio::test]
+async fn tool_concurrency_bounds_in_flight() -> color_eyre::Result<()> {
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let most = Arc::new(AtomicUsize::new(0));
+    let body = include_str!("fixtures/sina.txt");
+    let tool = sina::Sina::init_with_probe(reqwest::Client::new(), &Probe::Skip)
+        .await?
+        .with_base_url(serve_tracked(body, in_flight, most.clone()).await?);
+    let concurrency = search::all::parse_tool_concurrency("sina=2, hexun=8")?;
+
+    assert_eq!(concurrency.get(&Tool::HeXun), Some(&8));
+    assert!(search::all::parse_tool_concurrency("sina").is_err());
+
+    let tool = All {
+        sources: vec![Source::Sina(tool)],
+        stable: true,
+        ..Default::default()
+    }
+    .with_tool_concurrency(&concurrency);
+    let keywords = vec!["茅台".to_string(); 6];
+    let results = Batch::default()
+        .with_concurrency(6)
+        .run(&tool, Input::default(), keywords, &mut |_: Progress<'_>| {
+            ControlFlow::Continue(())
+        })
+        .await;
+
+    assert!(results.iter().all(|(_, stock)| stock.is_some()));
+    assert_eq!(most.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[test]
+fn construct_inputs_without_panic() -> color_eyre::Result<()> {
+    let cfi = cfi::Input::default();
+    let hexun = hexun::Input::default();
+
+    assert_eq!(cfi.longtime.len(), 17);
+    assert!(cfi.longtime.bytes().all(|v| v.is_ascii_digit()));
+    assert!(hexun.ty.starts_with("stock?math=0."));
+    assert!(sina::Input::try_default()?.count > 0);
+    assert!(sohu::Input::try_default()?.time > 0);
+    assert!(sina::Input::new("茅台").count > 0);
+    assert!(search::unix_millis()? > 0);
+    Ok(())
+}
@@ -0,0 +1,496 @@
+use search::{
+    Board, Confidence, Exchange, MatchPreference, NormalizeStyle, Script, SearchOptions,
+    SecurityKind, Stock, Tool, Valid,
+};
+
+#[test]
+fn dedup_keeps_first_seen_order() {
+    let stocks = vec![
+        Stock::new("贵州茅台".into(), "600519".into(), Exchange::ShangHai),
+        Stock::new("平安银行".into(), "000001".into(), Exchange::ShenZhen),
+        Stock::new("茅台".into(), "600519".into(), Exchange::ShangHai),
+    ];
+    let stocks = search::dedup_stocks(stocks);
+
+    assert_eq!(stocks.len(), 2);
+    assert_eq!(stocks[0].name, "贵州茅台");
+    assert_eq!(stocks[1].code, "000001");
+}
+
+#[test]
+fn validate_code_against_exchange() {
+    let stock = Stock::new("贵州茅台".into(), "600519".into(), Exchange::ShangHai);
+
+    assert!(stock.validate().is_ok());
+
+    let stock = Stock::new("中芯国际".into(), "688981".into(), Exchange::ShenZhen);
+
+    assert!(stock.validate().is_err());
+}
+
+#[test]
+fn reject_truncated_mainland_code() {
+    let err = Stock::try_from("60051").unwrap_err();
+
+    assert!(err.to_string().contains("truncated"));
+    assert_eq!(search::normalize_stock_number("60051"), None);
+    assert!(search::validate_code_shape("6005a9").is_err());
+}
+
+#[test]
+fn accept_six_digit_code() -> color_eyre::Result<()> {
+    let stock = Stock::try_from("600519")?;
+
+    assert_eq!(stock.exchange, Exchange::ShangHai);
+    assert_eq!(
+        search::normalize_stock_number("600519").as_deref(),
+        Some("1600519")
+    );
+    assert_eq!(Stock::try_from("00700")?.exchange, Exchange::HongKong);
+    Ok(())
+}
+
+#[test]
+fn limit_keeps_source_order() {
+    let outputs = vec!["600519", "000001", "00700", "300750"];
+    let options = SearchOptions::default().with_limit(Some(2));
+
+    assert_eq!(options.truncate(outputs.clone()), vec!["600519", "000001"]);
+    assert_eq!(SearchOptions::default().truncate(outputs.clone()), outputs);
+}
+
+#[test]
+fn classify_chinext_board() -> color_eyre::Result<()> {
+    for code in ["300750", "301029"] {
+        let stock = Stock::try_from(code)?;
+
+        assert_eq!(stock.exchange, Exchange::ShenZhen);
+        assert_eq!(stock.board(), Board::ChiNext);
+    }
+    assert_eq!(Stock::try_from("000001")?.board(), Board::Main);
+    assert_eq!(Stock::try_from("688981")?.board(), Board::Star);
+    assert_eq!(Stock::try_from("303001")?.board(), Board::ChiNext);
+    // the rest of the 2-digit prefixes are the main board
+    assert_eq!(Stock::try_from("304001")?.board(), Board::Main);
+    assert_eq!(Stock::try_from("680001")?.board(), Board::Main);
+    // truncated ChiNext codes must not be taken as HK
+    assert!(Stock::try_from("30075").is_err());
+    assert!(Stock::try_from("30102").is_err());
+    Ok(())
+}
+
+#[test]
+fn pad_hk_code_in_context() {
+    for code in ["700", "0700", "00700"] {
+        assert_eq!(
+            search::normalize_stock_number_in(code, Some(Exchange::HongKong)).as_deref(),
+            Some("500700")
+        );
+        assert_eq!(
+            Stock::new("腾讯控股".into(), code.into(), Exchange::HongKong).normalize(),
+            "500700"
+        );
+    }
+    assert_eq!(search::pad_hk_code("600519"), None);
+    assert_eq!(
+        search::normalize_stock_number_in("600519", Some(Exchange::HongKong)),
+        None
+    );
+}
+
+#[test]
+fn gbk_urlencode_keyword() {
+    assert_eq!(
+        search::gbk_urlencode("平安银行"),
+        "%C6%BD%B0%B2%D2%F8%D0%D0"
+    );
+    assert_eq!(search::gbk_urlencode("600519"), "600519");
+}
+
+#[test]
+fn allowed_exchanges_skip_others() {
+    let options = SearchOptions::default()
+        .with_allowed_exchanges(vec![Exchange::ShangHai, Exchange::ShenZhen]);
+    let candidates: Vec<_> = [
+        Stock::new("腾讯控股".into(), "00700".into(), Exchange::HongKong),
+        Stock::new("北交所".into(), "430047".into(), Exchange::BeiJing),
+        Stock::new("平安银行".into(), "000001".into(), Exchange::ShenZhen),
+    ]
+    .into_iter()
+    .filter_map(|v| options.accept(v))
+    .collect();
+
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].code, "000001");
+
+    // the HK fallback is skipped when HK is not allowed
+    let hk = Stock::new("腾讯控股".into(), "00700".into(), Exchange::HongKong);
+
+    assert!(options.accept(hk).is_none());
+}
+
+#[test]
+fn match_keyword_against_aliases() {
+    let mut vanke = Stock::new("万科A".into(), "000002".into(), Exchange::ShenZhen);
+
+    vanke.aliases = vec!["万科企业".into()];
+
+    let candidates = vec![
+        Stock::new("招商蛇口".into(), "001979".into(), Exchange::ShenZhen),
+        vanke,
+    ];
+    let options = SearchOptions::default();
+
+    assert_eq!(
+        options.rank("万科企业", candidates.clone())[0].code,
+        "001979"
+    );
+
+    let options = options.with_also_match_aliases(true);
+
+    assert!(!options.matches(&candidates[1], "万科"));
+    assert_eq!(options.rank("万科企业", candidates)[0].code, "000002");
+}
+
+fn ah_candidates() -> Vec<Stock> {
+    vec![
+        Stock::new("腾讯控股ADR".into(), "TCEHY".into(), Exchange::UnitedStates),
+        Stock::new("招商银行".into(), "03968".into(), Exchange::HongKong),
+        Stock::new("招商银行".into(), "600036".into(), Exchange::ShangHai),
+    ]
+}
+
+#[test]
+fn prefer_a_share_first() {
+    let options = SearchOptions::default();
+    let selected = options.select(ah_candidates());
+
+    assert_eq!(options.prefer, MatchPreference::AShareFirst);
+    assert_eq!(selected.map(|v| v.code), Some("600036".into()));
+}
+
+#[test]
+fn prefer_h_share_first() {
+    let options = SearchOptions::default().with_prefer(MatchPreference::HShareFirst);
+
+    assert_eq!(
+        options.select(ah_candidates()).map(|v| v.code),
+        Some("03968".into())
+    );
+    // without the HK listing the mainland stock goes before the US stock
+    assert_eq!(
+        options
+            .select(
+                ah_candidates()
+                    .into_iter()
+                    .filter(|v| v.code != "03968")
+                    .collect()
+            )
+            .map(|v| v.code),
+        Some("600036".into())
+    );
+}
+
+#[test]
+fn prefer_first_any() -> color_eyre::Result<()> {
+    let options = SearchOptions::default().with_prefer("any".parse()?);
+
+    assert_eq!(
+        options.select(ah_candidates()).map(|v| v.code),
+        Some("TCEHY".into())
+    );
+    assert!(options.select(vec![]).is_none());
+    Ok(())
+}
+
+#[test]
+fn reconcile_conflicting_names() {
+    let with_source = |name: &str, code: &str, tool| {
+        let mut stock = Stock::new(name.into(), code.into(), Exchange::ShenZhen);
+
+        stock.source = Some(tool);
+        stock
+    };
+    let mut stocks = vec![
+        with_source("ST万科", "000002", Tool::Sina),
+        with_source("平安银行", "000001", Tool::Sina),
+        with_source("万科A", "000002", Tool::CnInfo),
+        with_source("平安银行", "000001", Tool::HeXun),
+    ];
+    let conflicts = search::reconcile_names(&mut stocks, Tool::CnInfo);
+
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].normalized, "0000002");
+    assert_eq!(
+        conflicts[0].names,
+        [
+            ("ST万科".to_string(), Some(Tool::Sina)),
+            ("万科A".to_string(), Some(Tool::CnInfo))
+        ]
+    );
+    assert_eq!(stocks[0].name, "万科A");
+    assert_eq!(stocks[2].name, "万科A");
+
+    // the first seen name is kept without the authority
+    let mut stocks = vec![
+        with_source("ST万科", "000002", Tool::Sina),
+        with_source("万科A", "000002", Tool::HeXun),
+    ];
+    let conflicts = search::reconcile_names(&mut stocks, Tool::CnInfo);
+
+    assert_eq!(conflicts[0].chosen, "ST万科");
+    assert_eq!(stocks[1].name, "ST万科");
+}
+
+#[test]
+fn yahoo_suffix_of_each_exchange() -> color_eyre::Result<()> {
+    let yahoo =
+        |code: &str, exchange| Stock::new(String::default(), code.into(), exchange).to_yahoo();
+
+    assert_eq!(yahoo("600519", Exchange::ShangHai), "600519.SS");
+    assert_eq!(yahoo("000001", Exchange::ShenZhen), "000001.SZ");
+    assert_eq!(yahoo("830799", Exchange::BeiJing), "830799.BJ");
+    assert_eq!(yahoo("700", Exchange::HongKong), "0700.HK");
+    assert_eq!(yahoo("09988", Exchange::HongKong), "9988.HK");
+    assert_eq!(yahoo("aapl", Exchange::UnitedStates), "AAPL");
+    assert_eq!("yahoo".parse::<NormalizeStyle>()?, NormalizeStyle::Yahoo);
+    // the suffix of yahoo is different from the suffix style
+    assert_eq!(
+        Stock::new(String::default(), "600519".into(), Exchange::ShangHai)
+            .normalize_with(NormalizeStyle::Suffix),
+        "600519.SH"
+    );
+    assert_eq!(
+        search::block::parse_code("600519.SS")?.exchange,
+        Exchange::ShangHai
+    );
+    Ok(())
+}
+
+#[test]
+fn code_prefixes_cover_the_mainland() {
+    let prefixes = search::code_prefixes();
+
+    for prefix in prefixes {
+        let code = format!("{:0<6}", prefix.prefix);
+        let mut stock = Stock::new(String::default(), code.clone(), prefix.exchange);
+
+        stock.kind = prefix.kind;
+
+        assert!(stock.validate().is_ok(), "{code} of {:?}", prefix.exchange);
+        assert_eq!(stock.board(), prefix.board, "{code}");
+    }
+    // every two digits accepted by the exchanges is in the table
+    for head in 0..100 {
+        let code = format!("{head:02}0000");
+
+        for exchange in [Exchange::ShangHai, Exchange::ShenZhen, Exchange::BeiJing] {
+            let listed = prefixes.iter().any(|v| {
+                v.exchange == exchange
+                    && v.kind == SecurityKind::Stock
+                    && code.starts_with(v.prefix)
+            });
+
+            assert_eq!(
+                exchange.valid(&code).is_some(),
+                listed,
+                "{code} of {exchange:?}"
+            );
+        }
+    }
+    for board in [Board::Main, Board::Star, Board::ChiNext, Board::BeiJing] {
+        assert!(prefixes.iter().any(|v| v.board == board), "{board:?}");
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Input(String);
+
+impl QueryInput for Input {
+    fn set_keyword(&mut self, keyword: String) {
+        self.0 = keyword;
+    }
+
+    fn keyword(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A source returns the A shares of 平安 in the given order.
+struct PingAn(Vec<(&'static str, &'static str, Exchange)>);
+
+impl Search for PingAn {
+    type Input = Input;
+    type Output = Stock;
+
+    async fn search_all(&self, _: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
+        Ok(self
+            .0
+            .iter()
+            .map(|(name, code, exchange)| Stock::new(name.to_string(), code.to_string(), *exchange))
+            .collect())
+    }
+}
+
+#[tokio::test]
+async fn tie_break_a_shares() -> color_eyre::Result<()> {
+    let stocks = [
+        ("平安银行", "000001", Exchange::ShenZhen),
+        ("中国平安", "601318", Exchange::ShangHai),
+    ];
+    let forward = PingAn(stocks.to_vec());
+    let backward = PingAn(stocks.iter().rev().copied().collect());
+
+    for tool in [&forward, &backward] {
+        // the exact name goes first
+        let exact = tool.search(&Input("平安银行".into())).await?;
+
+        assert_eq!(exact.code, "000001");
+    }
+
+    // the fuzzy matches keep the order of the source, even the first is of Shenzhen
+    let input = Input("平安".into());
+
+    assert_eq!(forward.search(&input).await?.code, "000001");
+    assert_eq!(backward.search(&input).await?.code, "601318");
+
+    // the exact matches are tied by the exchange, then the code
+    let tied = PingAn(vec![
+        ("平安", "000001", Exchange::ShenZhen),
+        ("平安", "601318", Exchange::ShangHai),
+    ]);
+
+    assert_eq!(tied.search(&input).await?.code, "601318");
+    Ok(())
+}
+
+#[tokio::test]
+async fn exchange_order_decides_the_mainland() -> color_eyre::Result<()> {
+    let tool = PingAn(vec![
+        ("招商银行", "600036", Exchange::ShangHai),
+        ("招商银行", "000036", Exchange::ShenZhen),
+    ]);
+    let input = Input("招商银行".into());
+
+    assert_eq!(tool.search(&input).await?.code, "600036");
+
+    let options =
+        SearchOptions::default().with_exchange_order(vec![Exchange::ShenZhen, Exchange::ShangHai]);
+
+    assert_eq!(
+        tool.search_with_options(&input, &options).await?.code,
+        "000036"
+    );
+    Ok(())
+}
+
+/// An output of a broken source, the code is always invalid.
+struct Broken(&'static str);
+
+impl TryFrom<Broken> for Stock {
+    type Error = color_eyre::Report;
+
+    fn try_from(value: Broken) -> Result<Self, Self::Error> {
+        Err(color_eyre::eyre::eyre!(
+            "Not a valid stock number: {}",
+            value.0
+        ))
+    }
+}
+
+struct BrokenSource;
+
+impl Search for BrokenSource {
+    type Input = Input;
+    type Output = Broken;
+
+    async fn search_all(&self, _: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
+        Ok(["1", "2", "3", "4"].into_iter().map(Broken).collect())
+    }
+}
+
+#[tokio::test]
+async fn report_the_conversion_errors() {
+    let err = BrokenSource
+        .search(&Input("茅台".into()))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(err.contains("All the 4 results failed to convert"), "{err}");
+    assert!(err.contains("Not a valid stock number: 3"), "{err}");
+    assert!(!err.contains("Not a valid stock number: 4"), "{err}");
+
+    // an empty source is still not found instead of a conversion error
+    let err = PingAn(vec![])
+        .search(&Input("茅台".into()))
+        .await
+        .unwrap_err();
+
+    assert!(!err.to_string().contains("convert"));
+}
+
+#[tokio::test]
+async fn reject_overlong_keyword() -> color_eyre::Result<()> {
+    let tool = PingAn(vec![("中国平安", "601318", Exchange::ShangHai)]);
+    let paragraph = "中国平安是一家综合金融服务集团".repeat(5);
+    let err = tool
+        .search(&Input(paragraph.clone()))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(
+        err.contains("75 characters, more than the limit 64"),
+        "{err}"
+    );
+
+    let options = SearchOptions::default().with_max_keyword_chars(Some(100));
+
+    assert_eq!(
+        tool.search_with_options(&Input(paragraph), &options)
+            .await?
+            .code,
+        "601318"
+    );
+    Ok(())
+}
+
+#[test]
+fn grade_the_confidence() {
+    let mut stock = Stock::new("贵州茅台".into(), "600519".into(), Exchange::ShangHai);
+
+    stock.aliases = vec!["茅台".into()];
+    assert_eq!(Confidence::of(" 贵州茅台 ", &stock), Confidence::High);
+    assert_eq!(Confidence::of("茅台", &stock), Confidence::High);
+    assert_eq!(Confidence::of("贵州", &stock), Confidence::Medium);
+    assert_eq!(Confidence::of("贵州茅台股份", &stock), Confidence::Medium);
+    assert_eq!(Confidence::of("600519", &stock), Confidence::Low);
+    assert_eq!(Confidence::of("gzmt", &stock), Confidence::Low);
+
+    // the codes passed through have no name
+    let code = Stock::new(String::default(), "600519".into(), Exchange::ShangHai);
+
+    assert_eq!(Confidence::of("600519", &code), Confidence::Low);
+    assert!(Confidence::Low < Confidence::High);
+}
+
+#[test]
+fn convert_the_hk_names() -> color_eyre::Result<()> {
+    let tencent = Stock::new("騰訊控股".into(), "00700".into(), Exchange::HongKong);
+    let simplified = SearchOptions::default().with_hk_script(Some("simp".parse()?));
+    let converted = simplified.accept(tencent).unwrap();
+
+    assert_eq!(converted.name, "腾讯控股");
+
+    let traditional = SearchOptions::default().with_hk_script(Some(Script::Traditional));
+
+    assert_eq!(traditional.accept(converted).unwrap().name, "騰訊控股");
+
+    // the mainland names are kept
+    let mainland = Stock::new("中國平安".into(), "601318".into(), Exchange::ShangHai);
+
+    assert_eq!(simplified.accept(mainland).unwrap().name, "中國平安");
+    assert!("big5".parse::<Script>().is_err());
+    Ok(())
+}
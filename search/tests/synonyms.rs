@@ -0,0 +1,82 @@
+use std::ops::ControlFlow;
+
+use search::batch::{Batch, Progress};
+use search::synonyms::Synonyms;
+use search::{Exchange, QueryInput, Search, Stock};
+
+#[derive(Debug, Clone, Default)]
+struct Input(String);
+
+impl QueryInput for Input {
+    fn set_keyword(&mut self, keyword: String) {
+        self.0 = keyword;
+    }
+
+    fn keyword(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A source only matches the full name.
+struct FullNameOnly;
+
+impl Search for FullNameOnly {
+    type Input = Input;
+    type Output = Stock;
+
+    async fn search_all(&self, input: &Self::Input) -> color_eyre::Result<Vec<Self::Output>> {
+        Ok(match input.0.as_str() {
+            "贵州茅台" => vec![Stock::new(
+                "贵州茅台".into(),
+                "600519".into(),
+                Exchange::ShangHai,
+            )],
+            _ => vec![],
+        })
+    }
+}
+
+#[test]
+fn parse_synonyms() -> color_eyre::Result<()> {
+    let synonyms = Synonyms::parse_csv("# comment\n茅台,贵州茅台\n\n招行，招商银行\n")?;
+
+    assert_eq!(synonyms.expand("茅台"), "贵州茅台");
+    assert_eq!(synonyms.expand("招行"), "招商银行");
+    assert_eq!(synonyms.expand("平安银行"), "平安银行");
+    assert!(Synonyms::parse_csv("茅台").is_err());
+    assert_eq!(Synonyms::bundled()?.expand("茅台"), "贵州茅台");
+    Ok(())
+}
+
+#[tokio::test]
+async fn expand_keyword_before_search() {
+    let keywords = vec!["茅台".to_string()];
+    let mut reporter = |_: Progress<'_>| ControlFlow::Continue(());
+    let raw = Batch::default()
+        .run(
+            &FullNameOnly,
+            Input::default(),
+            keywords.clone(),
+            &mut reporter,
+        )
+        .await;
+
+    assert!(raw[0].1.is_none());
+
+    let mut synonyms = Synonyms::default();
+
+    synonyms.insert("茅台", "贵州茅台");
+
+    let expanded = Batch::default()
+        .with_synonyms(synonyms)
+        .with_keep_query(true)
+        .run(&FullNameOnly, Input::default(), keywords, &mut reporter)
+        .await;
+    let (keyword, stock) = &expanded[0];
+    let stock = stock.as_ref().unwrap();
+
+    // the original keyword is kept
+    assert_eq!(keyword, "茅台");
+    assert_eq!(stock.query.as_deref(), Some("茅台"));
+    assert_eq!(stock.code, "600519");
+}